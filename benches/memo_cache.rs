@@ -1,9 +1,15 @@
-use criterion::{criterion_group, criterion_main, Criterion};
-use memo_cache::MemoCache;
+// The `HashMap` baselines do a lookup and an insertion, like the `MemoCache` benches.
+#![allow(clippy::unnecessary_get_then_check)]
+
+use criterion::{criterion_group, criterion_main, BenchmarkGroup, BenchmarkId, Criterion};
+use memo_cache::{
+    policy::{Fifo, Sieve},
+    EvictionPolicy, MemoCache, SoaMemoCache, TaggedMemoCache,
+};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 use rand_distr::{Distribution, Normal};
-use std::{collections::HashMap, ops::RangeInclusive, thread, time};
+use std::{collections::HashMap, hint::black_box, ops::RangeInclusive, thread, time};
 
 // Pseudo random number generator seed value (used for all benches).
 const RNG_SEED_VALUE: u64 = 42;
@@ -24,6 +30,17 @@ fn cache_size(capacity: usize) -> usize {
     capacity * (std::mem::size_of::<u32>() + std::mem::size_of::<f32>())
 }
 
+/// Get the `MemoCache` bench group name for an eviction policy and a key distribution. The default policy (FIFO) keeps
+/// the group names from before policies were pluggable, so earlier results remain comparable.
+fn memo_cache_group<P>(distribution: &str) -> String {
+    match std::any::type_name::<P>().rsplit("::").next().unwrap() {
+        "Fifo" => format!("MemoCache (size: {MEMO_CACHE_CAPACITY}) - {distribution} distribution"),
+        policy => format!(
+            "MemoCache (size: {MEMO_CACHE_CAPACITY}, policy: {policy}) - {distribution} distribution"
+        ),
+    }
+}
+
 fn fake_expensive_calculation() {
     thread::sleep(time::Duration::from_millis(1));
 }
@@ -70,13 +87,10 @@ fn bench_hash_map_uniform(c: &mut Criterion) {
 }
 
 // Fixed-size cache, uniform distribution.
-fn bench_memo_cache_uniform(c: &mut Criterion) {
-    let mut g = c.benchmark_group(format!(
-        "MemoCache (size: {}) - Uniform distribution",
-        MEMO_CACHE_CAPACITY
-    ));
+fn bench_memo_cache_uniform<P: EvictionPolicy<i32>>(c: &mut Criterion) {
+    let mut g = c.benchmark_group(memo_cache_group::<P>("Uniform"));
 
-    let mut cache = MemoCache::<_, _, MEMO_CACHE_CAPACITY>::new();
+    let mut cache = MemoCache::<_, _, MEMO_CACHE_CAPACITY, P>::new();
     let mut rng = ChaCha8Rng::seed_from_u64(RNG_SEED_VALUE);
     g.bench_function("narrow range", |b| {
         b.iter_batched(
@@ -93,7 +107,7 @@ fn bench_memo_cache_uniform(c: &mut Criterion) {
 
     println!("Used cache size: {} bytes", cache_size(cache.capacity()));
 
-    let mut cache = MemoCache::<_, _, MEMO_CACHE_CAPACITY>::new();
+    let mut cache = MemoCache::<_, _, MEMO_CACHE_CAPACITY, P>::new();
     let mut rng = ChaCha8Rng::seed_from_u64(RNG_SEED_VALUE);
     g.bench_function("wide range", |b| {
         b.iter_batched(
@@ -157,13 +171,10 @@ fn bench_hash_map_normal(c: &mut Criterion) {
 }
 
 // Fixed-size cache, normal distribution.
-fn bench_memo_cache_normal(c: &mut Criterion) {
-    let mut g = c.benchmark_group(format!(
-        "MemoCache (size: {}) - Normal distribution",
-        MEMO_CACHE_CAPACITY
-    ));
+fn bench_memo_cache_normal<P: EvictionPolicy<i32>>(c: &mut Criterion) {
+    let mut g = c.benchmark_group(memo_cache_group::<P>("Normal"));
 
-    let mut cache = MemoCache::<_, _, MEMO_CACHE_CAPACITY>::new();
+    let mut cache = MemoCache::<_, _, MEMO_CACHE_CAPACITY, P>::new();
     let mut rng = ChaCha8Rng::seed_from_u64(RNG_SEED_VALUE);
     let normal = Normal::new(0.0, NORMAL_VARIANCE_NARROW).unwrap();
     g.bench_function("narrow range", |b| {
@@ -181,7 +192,7 @@ fn bench_memo_cache_normal(c: &mut Criterion) {
 
     println!("Used cache size: {} bytes", cache_size(cache.capacity()));
 
-    let mut cache = MemoCache::<_, _, MEMO_CACHE_CAPACITY>::new();
+    let mut cache = MemoCache::<_, _, MEMO_CACHE_CAPACITY, P>::new();
     let mut rng = ChaCha8Rng::seed_from_u64(RNG_SEED_VALUE);
     let normal = Normal::new(0.0, NORMAL_VARIANCE_WIDE).unwrap();
     g.bench_function("wide range", |b| {
@@ -202,11 +213,118 @@ fn bench_memo_cache_normal(c: &mut Criterion) {
     g.finish();
 }
 
+// Create a string key that is relatively costly to compare (i.e. with a long common prefix).
+fn string_key(i: usize) -> String {
+    format!("some/long/common/key/prefix/{i:08}")
+}
+
+// Plain versus tagged key lookups in a full cache of string keys, for a single cache size.
+fn bench_lookup_size<const SIZE: usize>(g: &mut BenchmarkGroup<criterion::measurement::WallTime>) {
+    let mut plain = MemoCache::<String, u32, SIZE>::new();
+    let mut tagged = TaggedMemoCache::<String, u32, SIZE>::new();
+
+    for i in 0..SIZE {
+        plain.insert(string_key(i), 42);
+        tagged.insert(string_key(i), 42);
+    }
+
+    // The most recently inserted key is the last slot to be scanned.
+    let hit = string_key(SIZE - 1);
+    let miss = string_key(SIZE);
+
+    g.bench_with_input(BenchmarkId::new("MemoCache hit", SIZE), &hit, |b, k| {
+        b.iter(|| plain.get(black_box(k.as_str())).copied())
+    });
+    g.bench_with_input(
+        BenchmarkId::new("TaggedMemoCache hit", SIZE),
+        &hit,
+        |b, k| b.iter(|| tagged.get(black_box(k.as_str())).copied()),
+    );
+    g.bench_with_input(BenchmarkId::new("MemoCache miss", SIZE), &miss, |b, k| {
+        b.iter(|| plain.get(black_box(k.as_str())).copied())
+    });
+    g.bench_with_input(
+        BenchmarkId::new("TaggedMemoCache miss", SIZE),
+        &miss,
+        |b, k| b.iter(|| tagged.get(black_box(k.as_str())).copied()),
+    );
+}
+
+// Plain versus tagged key lookups, to find the cache size where tags start to pay off.
+fn bench_lookup_crossover(c: &mut Criterion) {
+    let mut g = c.benchmark_group("Key lookup (string keys) - Plain versus tagged");
+
+    bench_lookup_size::<4>(&mut g);
+    bench_lookup_size::<8>(&mut g);
+    bench_lookup_size::<16>(&mut g);
+    bench_lookup_size::<32>(&mut g);
+    bench_lookup_size::<64>(&mut g);
+    bench_lookup_size::<128>(&mut g);
+    bench_lookup_size::<256>(&mut g);
+    bench_lookup_size::<512>(&mut g);
+    bench_lookup_size::<1024>(&mut g);
+
+    g.finish();
+}
+
+// Value size for the large value benches [bytes].
+const LARGE_VALUE_SIZE: usize = 256;
+
+// Array-of-structures versus structure-of-arrays key lookups in a full cache with large values, for a single size.
+fn bench_large_value_size<const SIZE: usize>(
+    g: &mut BenchmarkGroup<criterion::measurement::WallTime>,
+) {
+    let mut aos = MemoCache::<u32, [u8; LARGE_VALUE_SIZE], SIZE>::new();
+    let mut soa = SoaMemoCache::<u32, [u8; LARGE_VALUE_SIZE], SIZE>::new();
+
+    for k in 0..SIZE as u32 {
+        aos.insert(k, [k as u8; LARGE_VALUE_SIZE]);
+        soa.insert(k, [k as u8; LARGE_VALUE_SIZE]);
+    }
+
+    // The most recently inserted key is the last slot to be scanned.
+    let hit = SIZE as u32 - 1;
+    let miss = SIZE as u32;
+
+    g.bench_with_input(BenchmarkId::new("MemoCache hit", SIZE), &hit, |b, k| {
+        b.iter(|| aos.get(black_box(k)).map(|v| v[0]))
+    });
+    g.bench_with_input(BenchmarkId::new("SoaMemoCache hit", SIZE), &hit, |b, k| {
+        b.iter(|| soa.get(black_box(k)).map(|v| v[0]))
+    });
+    g.bench_with_input(BenchmarkId::new("MemoCache miss", SIZE), &miss, |b, k| {
+        b.iter(|| aos.get(black_box(k)).map(|v| v[0]))
+    });
+    g.bench_with_input(
+        BenchmarkId::new("SoaMemoCache miss", SIZE),
+        &miss,
+        |b, k| b.iter(|| soa.get(black_box(k)).map(|v| v[0])),
+    );
+}
+
+// Array-of-structures versus structure-of-arrays key lookups with large values.
+fn bench_large_value(c: &mut Criterion) {
+    let mut g = c.benchmark_group(format!(
+        "Key lookup ({} byte values) - Array-of-structures versus structure-of-arrays",
+        LARGE_VALUE_SIZE
+    ));
+
+    bench_large_value_size::<16>(&mut g);
+    bench_large_value_size::<64>(&mut g);
+    bench_large_value_size::<256>(&mut g);
+
+    g.finish();
+}
+
 criterion_group!(
     benches,
     bench_hash_map_uniform,
-    bench_memo_cache_uniform,
+    bench_memo_cache_uniform::<Fifo>,
+    bench_memo_cache_uniform::<Sieve>,
     bench_hash_map_normal,
-    bench_memo_cache_normal
+    bench_memo_cache_normal::<Fifo>,
+    bench_memo_cache_normal::<Sieve>,
+    bench_lookup_crossover,
+    bench_large_value
 );
 criterion_main!(benches);
@@ -0,0 +1,67 @@
+use memo_cache::{policy::Lru, CsMemoCache, MemoCache, SyncMemoCache};
+use std::{sync::Mutex, thread, time};
+
+/// Global memo table behind a plain mutex, without lazy initialization (the cache constructor is `const`).
+///
+/// Policies with interior mutability (e.g. `Lru`) make the cache `!Sync`, but the mutex makes it shareable anyway.
+static MUTEX_CACHE: Mutex<MemoCache<u32, f64, 32, Lru>> = Mutex::new(MemoCache::new());
+
+/// Global memo table using the thread-safe cache, which does not hold its lock while calculating.
+static SYNC_CACHE: SyncMemoCache<u32, f64, 32, Lru> = SyncMemoCache::new();
+
+/// Global memo table using the interrupt-safe cache (e.g. for embedded targets), here using the critical section
+/// implementation of the standard library.
+static CS_CACHE: CsMemoCache<u32, f64, 32, Lru> = CsMemoCache::new();
+
+fn some_expensive_calculation(input: u32) -> f64 {
+    thread::sleep(time::Duration::from_millis(20)); // ...zzzZZzz...
+    f64::from(input).sqrt()
+}
+
+/// Memoized function, using the global memo table behind a mutex.
+///
+/// Note that the lock is held while calculating, so concurrent callers wait for each other.
+fn memoized_mutex(input: u32) -> f64 {
+    *MUTEX_CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(&input, |&i| some_expensive_calculation(i))
+}
+
+/// Memoized function, using the thread-safe global memo table.
+///
+/// Callers missing on the same key share a single calculation, others calculate in parallel.
+fn memoized_sync(input: u32) -> f64 {
+    SYNC_CACHE.get_or_insert_with(&input, |&i| some_expensive_calculation(i))
+}
+
+/// Memoized function, using the interrupt-safe global memo table.
+///
+/// The calculation runs outside of the critical section, so (on embedded targets) interrupts are not delayed.
+fn memoized_cs(input: u32) -> f64 {
+    CS_CACHE.get_or_insert_with(&input, |&i| some_expensive_calculation(i))
+}
+
+/// Call the memoized function from a number of threads.
+fn run(name: &'static str, memoized: fn(u32) -> f64) {
+    let now = time::Instant::now();
+
+    let handles: Vec<_> = (0..4)
+        .map(|t| {
+            thread::spawn(move || {
+                let sum: f64 = (0..100).map(|i| memoized((i * (t + 1)) % 16)).sum();
+                println!("{name}, thread {t}: sum {sum:.3}");
+            })
+        })
+        .collect();
+
+    handles.into_iter().for_each(|h| h.join().unwrap());
+
+    println!("{name}: done in {:?}", now.elapsed());
+}
+
+fn main() {
+    run("Mutex<MemoCache>", memoized_mutex);
+    run("SyncMemoCache", memoized_sync);
+    run("CsMemoCache", memoized_cs);
+}
@@ -0,0 +1,564 @@
+//! Memo cache for word-sized keys and values with lock-free reads and spinlocked writes, using per-slot sequence locks.
+
+use core::marker::PhantomData;
+
+#[cfg(not(loom))]
+use core::{
+    hint::spin_loop,
+    sync::atomic::{fence, AtomicU64, AtomicUsize, Ordering},
+};
+#[cfg(loom)]
+use loom::{
+    hint::spin_loop,
+    sync::atomic::{fence, AtomicU64, AtomicUsize, Ordering},
+};
+
+/// A `Copy` type that can be stored in (and restored from) a single 64-bit word, for use in an [`AtomicMemoCache`].
+///
+/// Implemented for the primitive integer and floating point types, `bool` and `char`. Implement it for other small
+/// types by packing them into a word.
+///
+/// # Examples
+///
+/// ```
+/// use memo_cache::Word;
+///
+/// #[derive(Clone, Copy, PartialEq, Eq)]
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// impl Word for Point {
+///     fn into_word(self) -> u64 {
+///         (self.x as u32 as u64) << 32 | self.y as u32 as u64
+///     }
+///
+///     fn from_word(word: u64) -> Self {
+///         Point {
+///             x: (word >> 32) as i32,
+///             y: word as i32,
+///         }
+///     }
+/// }
+///
+/// let p = Point { x: -1, y: 2 };
+///
+/// assert!(Point::from_word(p.into_word()) == p);
+/// ```
+pub trait Word: Copy {
+    /// Convert the value into a word.
+    fn into_word(self) -> u64;
+
+    /// Convert a word back into a value. Only called with words produced by [`into_word`](Self::into_word).
+    fn from_word(word: u64) -> Self;
+}
+
+macro_rules! impl_word_for_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Word for $t {
+                #[cfg_attr(feature = "inline-more", inline)]
+                fn into_word(self) -> u64 {
+                    self as u64
+                }
+
+                #[cfg_attr(feature = "inline-more", inline)]
+                fn from_word(word: u64) -> Self {
+                    word as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_word_for_integer!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl Word for f32 {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn into_word(self) -> u64 {
+        self.to_bits().into()
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn from_word(word: u64) -> Self {
+        f32::from_bits(word as u32)
+    }
+}
+
+impl Word for f64 {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn into_word(self) -> u64 {
+        self.to_bits()
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn from_word(word: u64) -> Self {
+        f64::from_bits(word)
+    }
+}
+
+impl Word for bool {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn into_word(self) -> u64 {
+        self.into()
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn from_word(word: u64) -> Self {
+        word != 0
+    }
+}
+
+impl Word for char {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn into_word(self) -> u64 {
+        u32::from(self).into()
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn from_word(word: u64) -> Self {
+        char::from_u32(word as u32).unwrap_or_default()
+    }
+}
+
+/// Sequence bit set while a slot is being written.
+const WRITING: usize = 0b01;
+
+/// Sequence bit set while a slot holds a key/value pair.
+const OCCUPIED: usize = 0b10;
+
+/// Sequence increment of every completed write, so a sequence value never repeats (barring wrap-around).
+const VERSION: usize = 0b100;
+
+/// A cache slot: a key/value pair guarded by a sequence lock.
+struct Slot {
+    seq: AtomicUsize,
+    key: AtomicU64,
+    value: AtomicU64,
+}
+
+impl Slot {
+    /// Create an empty slot.
+    #[cfg(not(loom))]
+    const fn new() -> Self {
+        Self {
+            seq: AtomicUsize::new(0),
+            key: AtomicU64::new(0),
+            value: AtomicU64::new(0),
+        }
+    }
+
+    /// Create an empty slot (loom atomics cannot be created in a constant context).
+    #[cfg(loom)]
+    fn new() -> Self {
+        Self {
+            seq: AtomicUsize::new(0),
+            key: AtomicU64::new(0),
+            value: AtomicU64::new(0),
+        }
+    }
+
+    /// Read the key/value words, or `None` if the slot is empty or being written. Retries on a torn read.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn read(&self) -> Option<(u64, u64)> {
+        loop {
+            let seq = self.seq.load(Ordering::Acquire);
+
+            // A slot being written no longer holds its previous pair, and does not hold its next pair yet.
+            if seq & (WRITING | OCCUPIED) != OCCUPIED {
+                return None;
+            }
+
+            let key = self.key.load(Ordering::Relaxed);
+            let value = self.value.load(Ordering::Relaxed);
+
+            // Order the word loads before the sequence check; a write that overlapped them changed the sequence.
+            fence(Ordering::Acquire);
+
+            if self.seq.load(Ordering::Relaxed) == seq {
+                return Some((key, value));
+            }
+
+            spin_loop();
+        }
+    }
+
+    /// Lock the slot for writing, waiting for other writers. Returns the sequence value before locking.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn lock(&self) -> usize {
+        loop {
+            let seq = self.seq.load(Ordering::Relaxed);
+
+            if seq & WRITING == 0
+                && self
+                    .seq
+                    .compare_exchange_weak(seq, seq | WRITING, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            {
+                // Order the sequence update before the word stores, so readers of the new words see it.
+                fence(Ordering::Release);
+                return seq;
+            }
+
+            spin_loop();
+        }
+    }
+
+    /// Unlock the slot after writing, given the sequence value returned by [`lock`](Self::lock).
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn unlock(&self, seq: usize, occupied: bool) {
+        let seq = (seq & !(WRITING | OCCUPIED)).wrapping_add(VERSION);
+
+        self.seq.store(
+            if occupied { seq | OCCUPIED } else { seq },
+            Ordering::Release,
+        );
+    }
+
+    /// Write a key/value pair.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn write(&self, key: u64, value: u64) {
+        let seq = self.lock();
+
+        self.key.store(key, Ordering::Relaxed);
+        self.value.store(value, Ordering::Relaxed);
+        self.unlock(seq, true);
+    }
+}
+
+/// A small, fixed-size key/value cache with FIFO retention, for word-sized keys and values, with lock-free reads and
+/// spinlocked writes.
+///
+/// All methods take `&self`, so the cache can be shared between threads (e.g. in a `static` item) without a mutex.
+/// Keys and values are restricted to `Copy` types that fit in a 64-bit word (see [`Word`]), and lookups return copies
+/// of values.
+///
+/// Every slot is guarded by its own sequence lock: readers never block, and retry a slot if a write overlapped their
+/// read, while writers claim slots through an atomic FIFO cursor. Concurrent misses on the same key may compute and
+/// store the value more than once, and a lookup that overlaps the replacement of a slot misses.
+///
+/// A cache without slots cannot hold any key, so `SIZE` must be at least one. A cache in a `static` without slots fails
+/// to compile:
+///
+/// ```compile_fail
+/// use memo_cache::AtomicMemoCache;
+///
+/// static CACHE: AtomicMemoCache<u64, f64, 0> = AtomicMemoCache::new();
+/// ```
+///
+/// Only available on targets that support 64-bit atomics.
+pub struct AtomicMemoCache<K, V, const SIZE: usize> {
+    slots: [Slot; SIZE],
+    cursor: AtomicUsize,
+    marker: PhantomData<(K, V)>,
+}
+
+impl<K, V, const SIZE: usize> AtomicMemoCache<K, V, SIZE>
+where
+    K: Word + Eq,
+    V: Word,
+{
+    /// Create a new cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::AtomicMemoCache;
+    ///
+    /// static CACHE: AtomicMemoCache<u64, f64, 16> = AtomicMemoCache::new();
+    /// ```
+    #[cfg(not(loom))]
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        const { assert!(SIZE > 0, "cache capacity must be non-zero") };
+
+        Self {
+            slots: [const { Slot::new() }; SIZE],
+            cursor: AtomicUsize::new(0),
+            marker: PhantomData,
+        }
+    }
+
+    /// Create a new cache (loom atomics cannot be created in a constant context).
+    #[cfg(loom)]
+    pub fn new() -> Self {
+        const { assert!(SIZE > 0, "cache capacity must be non-zero") };
+
+        Self {
+            slots: core::array::from_fn(|_| Slot::new()),
+            cursor: AtomicUsize::new(0),
+            marker: PhantomData,
+        }
+    }
+
+    /// Get the (fixed) capacity of the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::AtomicMemoCache;
+    ///
+    /// let c = AtomicMemoCache::<u64, f64, 8>::new();
+    ///
+    /// assert_eq!(c.capacity(), 8);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn capacity(&self) -> usize {
+        SIZE
+    }
+
+    /// Get the slot index and value for a given key, if found.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn find(&self, k: &K) -> Option<(usize, V)> {
+        self.slots.iter().enumerate().find_map(|(i, slot)| {
+            slot.read()
+                .filter(|&(key, _)| K::from_word(key) == *k)
+                .map(|(_, value)| (i, V::from_word(value)))
+        })
+    }
+
+    /// Claim the slot at the cursor, and advance the cursor.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn claim(&self) -> &Slot {
+        let i = self
+            .cursor
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |c| {
+                Some(if c + 1 == SIZE { 0 } else { c + 1 })
+            })
+            .unwrap_or_default();
+
+        &self.slots[i]
+    }
+
+    /// Insert a key/value pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::AtomicMemoCache;
+    ///
+    /// let c = AtomicMemoCache::<u64, f64, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, 4.2);
+    ///
+    /// assert_eq!(c.get(&42), Some(4.2));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn insert(&self, k: K, v: V) {
+        let (key, value) = (k.into_word(), v.into_word());
+
+        if let Some((i, _)) = self.find(&k) {
+            let slot = &self.slots[i];
+            let seq = slot.lock();
+
+            // The slot may have been replaced between finding and locking it.
+            if seq & OCCUPIED != 0 && K::from_word(slot.key.load(Ordering::Relaxed)) == k {
+                slot.value.store(value, Ordering::Relaxed);
+                slot.unlock(seq, true);
+                return;
+            }
+
+            slot.unlock(seq, seq & OCCUPIED != 0);
+        }
+
+        self.claim().write(key, value);
+    }
+
+    /// Returns `true` if the cache contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::AtomicMemoCache;
+    ///
+    /// let c = AtomicMemoCache::<u64, f64, 4>::new();
+    ///
+    /// assert_eq!(c.contains_key(&42), false);
+    ///
+    /// c.insert(42, 4.2);
+    ///
+    /// assert_eq!(c.contains_key(&42), true);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn contains_key(&self, k: &K) -> bool {
+        self.find(k).is_some()
+    }
+
+    /// Lookup a cache entry by key, returning a copy of the value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::AtomicMemoCache;
+    ///
+    /// let c = AtomicMemoCache::<u64, f64, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, 4.2);
+    ///
+    /// assert_eq!(c.get(&42), Some(4.2));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get(&self, k: &K) -> Option<V> {
+        self.find(k).map(|(_, v)| v)
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a copy of the found, or newly inserted value associated with the given key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::AtomicMemoCache;
+    ///
+    /// let c = AtomicMemoCache::<u64, f64, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// let v = c.get_or_insert_with(&42, |&k| k as f64 / 10.0);
+    ///
+    /// assert_eq!(v, 4.2);
+    /// assert_eq!(c.get(&42), Some(4.2));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_insert_with<F>(&self, k: &K, f: F) -> V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        if let Some(v) = self.get(k) {
+            return v;
+        }
+
+        let v = f(k);
+        self.claim().write(k.into_word(), v.into_word());
+        v
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a result with a copy of the found, or newly inserted value associated with the given key.
+    /// If `f` fails, the error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::AtomicMemoCache;
+    ///
+    /// let c = AtomicMemoCache::<u64, f64, 4>::new();
+    ///
+    /// let v = c.get_or_try_insert_with(&42, |&k| Ok::<_, &str>(k as f64 / 10.0));
+    ///
+    /// assert_eq!(v, Ok(4.2));
+    ///
+    /// let v = c.get_or_try_insert_with(&17, |_| Err("Dunno"));
+    ///
+    /// assert_eq!(v, Err("Dunno"));
+    /// assert_eq!(c.get(&17), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_try_insert_with<F, E>(&self, k: &K, f: F) -> Result<V, E>
+    where
+        F: FnOnce(&K) -> Result<V, E>,
+    {
+        if let Some(v) = self.get(k) {
+            return Ok(v);
+        }
+
+        let v = f(k)?;
+        self.claim().write(k.into_word(), v.into_word());
+        Ok(v)
+    }
+
+    /// Clear the cache.
+    ///
+    /// Slots are cleared one at a time, so pairs inserted by other threads meanwhile may be retained.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::AtomicMemoCache;
+    ///
+    /// let c = AtomicMemoCache::<u64, f64, 4>::new();
+    ///
+    /// c.insert(42, 4.2);
+    /// c.clear();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn clear(&self) {
+        // Restart filling at the first slot, like `MemoCache`.
+        self.cursor.store(0, Ordering::Relaxed);
+
+        for slot in &self.slots {
+            let seq = slot.lock();
+            slot.unlock(seq, false);
+        }
+    }
+}
+
+impl<K, V, const SIZE: usize> Default for AtomicMemoCache<K, V, SIZE>
+where
+    K: Word + Eq,
+    V: Word,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests_internal {
+    use super::*;
+
+    #[test]
+    fn test_sequence() {
+        let c = AtomicMemoCache::<u32, u32, 2>::new();
+
+        let seq = |i: usize| c.slots[i].seq.load(Ordering::Relaxed);
+
+        assert_eq!(seq(0), 0);
+
+        c.insert(1, 10);
+        assert_eq!(seq(0), VERSION | OCCUPIED);
+
+        // Updating a value in place bumps the version.
+        c.insert(1, 11);
+        assert_eq!(seq(0), (2 * VERSION) | OCCUPIED);
+
+        c.clear();
+        assert_eq!(seq(0), 3 * VERSION);
+        assert_eq!(seq(1), VERSION);
+    }
+
+    #[test]
+    fn test_cursor_wraps() {
+        let c = AtomicMemoCache::<u32, u32, 3>::new();
+
+        (0..4).for_each(|k| c.insert(k, k));
+
+        // The fourth key replaced the first one, like the FIFO policy of `MemoCache`.
+        assert_eq!(c.cursor.load(Ordering::Relaxed), 1);
+        assert_eq!(c.get(&0), None);
+        assert_eq!(c.slots[0].read(), Some((3, 3)));
+    }
+
+    #[test]
+    fn test_clear_resets_cursor() {
+        let c = AtomicMemoCache::<u32, u32, 3>::new();
+
+        (0..2).for_each(|k| c.insert(k, k));
+        c.clear();
+
+        // Slots are filled from the start again, like `MemoCache`.
+        (2..4).for_each(|k| c.insert(k, k));
+
+        assert_eq!(c.cursor.load(Ordering::Relaxed), 2);
+        assert_eq!(c.slots[0].read(), Some((2, 2)));
+        assert_eq!(c.slots[1].read(), Some((3, 3)));
+        assert_eq!(c.slots[2].read(), None);
+    }
+}
@@ -0,0 +1,278 @@
+//! Interrupt-safe memo cache, using critical sections.
+
+use crate::{EvictionPolicy, Fifo, MemoCache};
+use core::{borrow::Borrow, cell::RefCell};
+use critical_section::Mutex;
+
+/// A small, fixed-size, interrupt-safe key/value cache with retention management.
+///
+/// The cache is a [`MemoCache`] behind a [`critical_section::Mutex`], so all methods take `&self`, and the cache can
+/// be shared between interrupt handlers and the main loop (e.g. in a `static` item). Every access runs inside a
+/// critical section, and lookups return copies of values, as references into the cache cannot outlive the critical
+/// section. For other access, use [`with_cache`](Self::with_cache).
+///
+/// The critical section is not held while computing values in [`get_or_insert_with`](Self::get_or_insert_with) and
+/// [`get_or_try_insert_with`](Self::get_or_try_insert_with), so (slow) computations do not delay interrupts.
+///
+/// An implementation of the critical section must be provided by the application, see the
+/// [critical-section](https://crates.io/crates/critical-section) crate.
+pub struct CsMemoCache<K, V, const SIZE: usize, P = Fifo>
+where
+    P: EvictionPolicy<K>,
+{
+    cache: Mutex<RefCell<MemoCache<K, V, SIZE, P>>>,
+}
+
+impl<K, V, const SIZE: usize, P> CsMemoCache<K, V, SIZE, P>
+where
+    K: Clone + Eq,
+    V: Copy,
+    P: EvictionPolicy<K>,
+{
+    /// Create a new cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::CsMemoCache;
+    ///
+    /// static CACHE: CsMemoCache<u32, f32, 4> = CsMemoCache::new();
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        Self::with_policy(P::INIT)
+    }
+
+    /// Create a new cache, using the given eviction policy state (see
+    /// [`MemoCache::with_policy`](crate::MemoCache::with_policy)).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{policy::Random, CsMemoCache};
+    ///
+    /// let c = CsMemoCache::<u32, f32, 4, Random>::with_policy(Random::new(42));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn with_policy(policy: P) -> Self {
+        Self {
+            cache: Mutex::new(RefCell::new(MemoCache::with_policy(policy))),
+        }
+    }
+
+    /// Get the (fixed) capacity of the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::CsMemoCache;
+    ///
+    /// let c = CsMemoCache::<u32, f32, 8>::new();
+    ///
+    /// assert_eq!(c.capacity(), 8);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn capacity(&self) -> usize {
+        SIZE
+    }
+
+    /// Run `f` with direct access to the cache, inside a critical section.
+    ///
+    /// Accessing the cache from within `f` (other than through its argument) panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::CsMemoCache;
+    ///
+    /// let c = CsMemoCache::<u32, [u8; 4], 4>::new();
+    ///
+    /// c.insert(42, *b"Answ");
+    ///
+    /// // Borrow the value, instead of copying it.
+    /// assert_eq!(c.with_cache(|c| c.get(&42).map(|v| v[0])), Some(b'A'));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn with_cache<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut MemoCache<K, V, SIZE, P>) -> R,
+    {
+        critical_section::with(|cs| f(&mut self.cache.borrow_ref_mut(cs)))
+    }
+
+    /// Insert a key/value pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::CsMemoCache;
+    ///
+    /// let c = CsMemoCache::<u32, f32, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, 1.5);
+    ///
+    /// assert_eq!(c.get(&42), Some(1.5));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn insert(&self, k: K, v: V) {
+        self.with_cache(|c| c.insert(k, v));
+    }
+
+    /// Returns `true` if the cache contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::CsMemoCache;
+    ///
+    /// let c = CsMemoCache::<u32, f32, 4>::new();
+    ///
+    /// assert_eq!(c.contains_key(&42), false);
+    ///
+    /// c.insert(42, 1.5);
+    ///
+    /// assert_eq!(c.contains_key(&42), true);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.with_cache(|c| c.contains_key(k))
+    }
+
+    /// Lookup a cache entry by key, returning a copy of the value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::CsMemoCache;
+    ///
+    /// let c = CsMemoCache::<u32, f32, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, 1.5);
+    ///
+    /// assert_eq!(c.get(&42), Some(1.5));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.with_cache(|c| c.get(k).copied())
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a copy of the found, or newly inserted value associated with the given key.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// The lookup and the insertion each run in a critical section, but `f` runs outside of them, so `f` may use the
+    /// cache itself (e.g. for recursive memoization). If the value for the same key was inserted in the meantime (e.g.
+    /// by an interrupt handler), that value is kept and returned instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::CsMemoCache;
+    ///
+    /// let c = CsMemoCache::<u32, f32, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// let v = c.get_or_insert_with(&42, |_| 1.5);
+    ///
+    /// assert_eq!(v, 1.5);
+    /// assert_eq!(c.get(&42), Some(1.5));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_insert_with<F>(&self, k: &K, f: F) -> V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self.get(k) {
+            Some(v) => v,
+            None => {
+                let v = f(k);
+                self.with_cache(|c| *c.get_or_insert_with(k, |_| v))
+            }
+        }
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a result with a copy of the found, or newly inserted value associated with the given key.
+    /// If `f` fails, the error is returned.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// The lookup and the insertion each run in a critical section, but `f` runs outside of them, so `f` may use the
+    /// cache itself (e.g. for recursive memoization). If the value for the same key was inserted in the meantime (e.g.
+    /// by an interrupt handler), that value is kept and returned instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::CsMemoCache;
+    ///
+    /// let c = CsMemoCache::<u32, f32, 4>::new();
+    ///
+    /// let v = c.get_or_try_insert_with(&42, |_| Ok::<_, &str>(1.5));
+    ///
+    /// assert_eq!(v, Ok(1.5));
+    ///
+    /// let v = c.get_or_try_insert_with(&17, |_| Err("Dunno"));
+    ///
+    /// assert_eq!(v, Err("Dunno"));
+    /// assert_eq!(c.get(&17), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_try_insert_with<F, E>(&self, k: &K, f: F) -> Result<V, E>
+    where
+        F: FnOnce(&K) -> Result<V, E>,
+    {
+        match self.get(k) {
+            Some(v) => Ok(v),
+            None => {
+                let v = f(k)?;
+                Ok(self.with_cache(|c| *c.get_or_insert_with(k, |_| v)))
+            }
+        }
+    }
+
+    /// Clear the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::CsMemoCache;
+    ///
+    /// let c = CsMemoCache::<u32, f32, 4>::new();
+    ///
+    /// c.insert(42, 1.5);
+    /// c.insert(17, 2.5);
+    ///
+    /// c.clear();
+    ///
+    /// assert_eq!(c.contains_key(&42), false);
+    /// assert_eq!(c.contains_key(&17), false);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn clear(&self) {
+        self.with_cache(|c| c.clear());
+    }
+}
+
+impl<K, V, const SIZE: usize, P> Default for CsMemoCache<K, V, SIZE, P>
+where
+    K: Clone + Eq,
+    V: Copy,
+    P: EvictionPolicy<K>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,265 @@
+//! Memo cache with a capacity chosen at runtime, allocated on the heap.
+
+use crate::{
+    raw::{RawBoxedMemoCache, RawMemoCache},
+    EvictionPolicy, Fifo, Slot,
+};
+use core::{borrow::Borrow, marker::PhantomData};
+
+/// A small, fixed-size, heap-allocated key/value cache with retention management, with a capacity chosen at runtime.
+///
+/// The slots are allocated once on construction, after which the cache never grows or reallocates. The API and
+/// eviction semantics are identical to [`MemoCache`](crate::MemoCache).
+pub struct DynMemoCache<K, V, P = Fifo>
+where
+    P: EvictionPolicy<K>,
+{
+    raw: RawBoxedMemoCache<K, V, P>,
+}
+
+impl<K, V, P> DynMemoCache<K, V, P>
+where
+    K: Clone + Eq,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    /// Create a new cache with the given capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::DynMemoCache;
+    ///
+    /// let c = DynMemoCache::<u32, String>::new(4);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn new(capacity: usize) -> Self {
+        Self::with_policy(capacity, P::INIT)
+    }
+
+    /// Create a new cache with the given capacity, using the given eviction policy state (see
+    /// [`MemoCache::with_policy`](crate::MemoCache::with_policy)).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{policy::Random, DynMemoCache};
+    ///
+    /// let c = DynMemoCache::<u32, String, Random>::with_policy(4, Random::new(42));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn with_policy(capacity: usize, policy: P) -> Self {
+        assert!(capacity > 0, "cache capacity must be non-zero");
+
+        Self {
+            raw: RawMemoCache {
+                buffer: (0..capacity).map(|_| Slot::EMPTY).collect(),
+                states: (0..capacity).map(|_| P::SLOT_INIT).collect(),
+                policy,
+                len: 0,
+                marker: PhantomData,
+            },
+        }
+    }
+
+    /// Get the (fixed) capacity of the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::DynMemoCache;
+    ///
+    /// let c = DynMemoCache::<u32, String>::new(8);
+    ///
+    /// assert_eq!(c.capacity(), 8);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn capacity(&self) -> usize {
+        self.raw.capacity()
+    }
+
+    /// Insert a key/value pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::DynMemoCache;
+    ///
+    /// let mut c = DynMemoCache::<u32, &str>::new(4);
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn insert(&mut self, k: K, v: V) {
+        self.raw.insert(k, v);
+    }
+
+    /// Returns `true` if the cache contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::DynMemoCache;
+    ///
+    /// let mut c = DynMemoCache::<u32, &str>::new(4);
+    ///
+    /// assert_eq!(c.contains_key(&42), false);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.contains_key(&42), true);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.raw.contains_key(k)
+    }
+
+    /// Lookup a cache entry by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::DynMemoCache;
+    ///
+    /// let mut c = DynMemoCache::<u32, &str>::new(4);
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.raw.get(k)
+    }
+
+    /// Lookup a cache entry by key (for mutation).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::DynMemoCache;
+    ///
+    /// let mut c = DynMemoCache::<u32, &str>::new(4);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// if let Some(v) = c.get_mut(&42) {
+    ///     *v = "Another Answer";
+    /// }
+    ///
+    /// assert_eq!(c.get(&42), Some(&"Another Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.raw.get_mut(k)
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a reference to the found, or newly inserted value associated with the given key.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::DynMemoCache;
+    ///
+    /// let mut c = DynMemoCache::<u32, &str>::new(4);
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// let v = c.get_or_insert_with(&42, |_| "The Answer");
+    ///
+    /// assert_eq!(v, &"The Answer");
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_insert_with<F>(&mut self, k: &K, f: F) -> &V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        self.raw.get_or_insert_with(k, f)
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a result with a reference to the found, or newly inserted value associated with the given key.
+    /// If `f` fails, the error is returned.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::DynMemoCache;
+    ///
+    /// let mut c = DynMemoCache::<u32, &str>::new(4);
+    ///
+    /// let v = c.get_or_try_insert_with(&42, |_| Ok::<_, &str>("The Answer"));
+    ///
+    /// assert_eq!(v, Ok(&"The Answer"));
+    ///
+    /// let v = c.get_or_try_insert_with(&17, |_| Err("Dunno"));
+    ///
+    /// assert_eq!(v, Err("Dunno"));
+    /// assert_eq!(c.get(&17), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_try_insert_with<F, E>(&mut self, k: &K, f: F) -> Result<&V, E>
+    where
+        F: FnOnce(&K) -> Result<V, E>,
+    {
+        self.raw.get_or_try_insert_with(k, f)
+    }
+
+    /// Clear the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::DynMemoCache;
+    ///
+    /// let mut c = DynMemoCache::<u32, &str>::new(4);
+    ///
+    /// c.insert(42, "The Answer");
+    /// c.clear();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn clear(&mut self) {
+        self.raw.clear();
+    }
+}
+
+impl<K, V, P> Drop for DynMemoCache<K, V, P>
+where
+    P: EvictionPolicy<K>,
+{
+    fn drop(&mut self) {
+        self.raw.drop_used();
+    }
+}
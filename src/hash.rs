@@ -0,0 +1,112 @@
+//! Key hashing, used where policies or lookups need key fingerprints.
+
+use core::hash::{Hash, Hasher};
+
+/// Multiplier of the FxHash hashing function.
+const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+/// Multiplier remixing key hashes into indices (the 64-bit golden ratio).
+const INDEX_MIX: u64 = 0x9e_37_79_b9_7f_4a_7c_15;
+
+/// A small, fast, non-cryptographic hasher (FxHash, as used in `rustc`).
+#[derive(Default)]
+struct FxHasher {
+    hash: u64,
+}
+
+impl FxHasher {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+
+        for c in &mut chunks {
+            // SAFETY: Exact chunks are 8 bytes long.
+            self.add(u64::from_le_bytes(unsafe {
+                c.try_into().unwrap_unchecked()
+            }));
+        }
+
+        let rest = chunks.remainder();
+
+        if !rest.is_empty() {
+            let mut word = [0u8; 8];
+            word[..rest.len()].copy_from_slice(rest);
+            self.add(u64::from_le_bytes(word));
+        }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn write_u8(&mut self, i: u8) {
+        self.add(i as u64);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn write_u16(&mut self, i: u16) {
+        self.add(i as u64);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn write_u32(&mut self, i: u32) {
+        self.add(i as u64);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn write_u64(&mut self, i: u64) {
+        self.add(i);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn write_usize(&mut self, i: usize) {
+        self.add(i as u64);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn finish(&self) -> u64 {
+        // FxHash leaves the low bits poorly mixed, so finish with the MurmurHash3 avalanche step.
+        let mut h = self.hash;
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        h ^ (h >> 33)
+    }
+}
+
+/// Hash a key. Borrowed forms of a key hash to the same value as the key itself.
+#[cfg_attr(feature = "inline-more", inline)]
+pub(crate) fn hash<Q>(k: &Q) -> u64
+where
+    Q: Hash + ?Sized,
+{
+    let mut h = FxHasher::default();
+    k.hash(&mut h);
+    h.finish()
+}
+
+/// Get a non-zero 32-bit fingerprint of a key, leaving zero available to mark unused entries.
+#[cfg_attr(feature = "inline-more", inline)]
+pub(crate) fn fingerprint<Q>(k: &Q) -> u32
+where
+    Q: Hash + ?Sized,
+{
+    (hash(k) >> 32) as u32 | 1
+}
+
+/// Map a key to an index in `0..n` (e.g. of a set or shard).
+///
+/// The hash is remixed first, and the index is taken from its high bits, so the index does not correlate with the hash
+/// bits used elsewhere (e.g. by the TinyLFU sketch of a policy of the selected set or shard).
+#[cfg_attr(feature = "inline-more", inline)]
+pub(crate) fn index<Q>(k: &Q, n: usize) -> usize
+where
+    Q: Hash + ?Sized,
+{
+    ((hash(k).wrapping_mul(INDEX_MIX) as u128 * n as u128) >> 64) as usize
+}
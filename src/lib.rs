@@ -1,6 +1,56 @@
 #![no_std]
 
-use core::borrow::Borrow;
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+extern crate std;
+
+use core::{borrow::Borrow, marker::PhantomData, mem::MaybeUninit};
+use raw::{RawArrayMemoCache, RawMemoCache};
+
+#[cfg(target_has_atomic = "64")]
+mod atomic;
+#[cfg(feature = "critical-section")]
+mod cs;
+#[cfg(feature = "alloc")]
+mod dynamic;
+mod hash;
+#[cfg(feature = "std")]
+mod local;
+pub mod policy;
+mod raw;
+mod reference;
+mod set_assoc;
+#[cfg(feature = "std")]
+mod sharded;
+mod soa;
+mod stats;
+#[cfg(feature = "std")]
+mod sync;
+mod tagged;
+
+#[cfg(target_has_atomic = "64")]
+pub use atomic::{AtomicMemoCache, Word};
+#[cfg(feature = "critical-section")]
+pub use cs::CsMemoCache;
+#[cfg(feature = "alloc")]
+pub use dynamic::DynMemoCache;
+#[cfg(feature = "std")]
+pub use local::ThreadLocalMemo;
+#[doc(hidden)]
+#[cfg(feature = "std")]
+pub use local::{LocalMemoCache, LocalStats};
+pub use policy::{EvictionPolicy, Fifo, SlotKeys};
+pub use reference::MemoCacheRef;
+pub use set_assoc::SetAssocMemoCache;
+#[cfg(feature = "std")]
+pub use sharded::ShardedMemoCache;
+pub use soa::SoaMemoCache;
+pub use stats::CacheStats;
+#[cfg(feature = "std")]
+pub use sync::{SyncMemoCache, SyncMemoCacheGuard};
+pub use tagged::TaggedMemoCache;
 
 /// Key equivalence trait, to support `Borrow` types as keys.
 trait Equivalent<K: ?Sized> {
@@ -18,69 +68,76 @@ where
     }
 }
 
-/// A single key/value slot used in the cache.
-#[derive(Clone, PartialEq)]
-enum KeyValueSlot<K, V> {
-    Used((K, V)),
-    Empty,
-}
-
-impl<K, V> KeyValueSlot<K, V> {
-    /// Check a used slot key for equivalence.
-    #[cfg_attr(feature = "inline-more", inline)]
-    fn is_key<Q>(&self, k: &Q) -> bool
-    where
-        Q: Equivalent<K> + ?Sized,
-    {
-        if let KeyValueSlot::Used(kv) = self {
-            k.equivalent(&kv.0)
-        } else {
-            false
-        }
-    }
-
-    /// Get the value of a used slot.
-    #[cfg_attr(feature = "inline-more", inline)]
-    fn get_value(&self) -> Option<&V> {
-        if let KeyValueSlot::Used(kv) = self {
-            Some(&kv.1)
-        } else {
-            None
-        }
-    }
-
-    /// Get the value of a used slot (for mutation).
+/// A single key/value slot used in the cache, initialized only if used.
+///
+/// Slots carry no occupancy flag (i.e. no enum discriminant and padding): they are filled front to back and are never
+/// emptied individually, so the cache tracks occupancy by the number of used slots.
+///
+/// Slots are only created directly to provide the storage of a [`MemoCacheRef`], and can be created in constant
+/// context (e.g. for a `static` buffer).
+///
+/// # Examples
+///
+/// ```
+/// use memo_cache::Slot;
+///
+/// let slots: [Slot<u32, f32>; 8] = [Slot::EMPTY; 8];
+/// ```
+#[repr(transparent)]
+pub struct Slot<K, V>(MaybeUninit<(K, V)>);
+
+impl<K, V> Slot<K, V> {
+    /// An empty slot.
+    pub const EMPTY: Self = Self(MaybeUninit::uninit());
+
+    /// Create an empty slot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::Slot;
+    ///
+    /// const SLOT: Slot<u32, f32> = Slot::new();
+    /// ```
     #[cfg_attr(feature = "inline-more", inline)]
-    fn get_value_mut(&mut self) -> Option<&mut V> {
-        if let KeyValueSlot::Used(kv) = self {
-            Some(&mut kv.1)
-        } else {
-            None
-        }
+    pub const fn new() -> Self {
+        Self::EMPTY
     }
+}
 
-    /// Update the value of a used slot.
-    #[cfg_attr(feature = "inline-more", inline)]
-    fn update_value(&mut self, v: V) {
-        if let KeyValueSlot::Used(kv) = self {
-            kv.1 = v
-        }
+impl<K, V> Default for Slot<K, V> {
+    fn default() -> Self {
+        Self::EMPTY
     }
 }
 
-/// A small, fixed-size, heap-allocated key/value cache with retention management.
-pub struct MemoCache<K, V, const SIZE: usize> {
-    buffer: [KeyValueSlot<K, V>; SIZE],
-    cursor: usize,
+/// A small, fixed-size key/value cache with retention management.
+///
+/// The slots are stored inline (i.e. the cache does not allocate), with the capacity given by `SIZE`. See
+/// `DynMemoCache` (with the `alloc` feature) for a cache with a capacity chosen at runtime.
+///
+/// The slot to be replaced when the cache is full is selected by the eviction policy `P` (FIFO by default, see the
+/// [`policy`] module for alternatives).
+pub struct MemoCache<K, V, const SIZE: usize, P = Fifo>
+where
+    P: EvictionPolicy<K>,
+{
+    raw: RawArrayMemoCache<K, V, SIZE, P>,
 }
 
-impl<K, V, const SIZE: usize> MemoCache<K, V, SIZE>
+impl<K, V, const SIZE: usize, P> MemoCache<K, V, SIZE, P>
 where
     K: Clone + Eq,
     V: Clone,
+    P: EvictionPolicy<K>,
 {
     /// Create a new cache.
     ///
+    /// The constructor is `const`, so a cache can be placed in a `static` item (e.g. behind a mutex) without lazy
+    /// initialization. For global memo tables, there are also ready-made wrappers with `const` constructors, which do
+    /// not hold their lock while calculating values: `SyncMemoCache` (with the `std` feature), and `CsMemoCache` for
+    /// embedded targets (with the `critical-section` feature).
+    ///
     /// # Examples
     ///
     /// ```
@@ -88,11 +145,52 @@ where
     ///
     /// let c = MemoCache::<u32, String, 4>::new();
     /// ```
+    ///
+    /// A global memo table:
+    ///
+    /// ```
+    /// use memo_cache::MemoCache;
+    /// use std::sync::Mutex;
+    ///
+    /// static CACHE: Mutex<MemoCache<u64, u64, 16>> = Mutex::new(MemoCache::new());
+    ///
+    /// fn square(x: u64) -> u64 {
+    ///     *CACHE.lock().unwrap().get_or_insert_with(&x, |&x| x * x)
+    /// }
+    ///
+    /// assert_eq!(square(7), 49);
+    /// assert!(CACHE.lock().unwrap().contains_key(&7));
+    /// ```
     #[cfg_attr(feature = "inline-more", inline)]
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
+        Self::with_policy(P::INIT)
+    }
+
+    /// Create a new cache, using the given eviction policy state.
+    ///
+    /// This is needed for policies with parameters that have no default, e.g. the seed of [`Random`](policy::Random).
+    /// Other policies start from their initial state when using [`new`](Self::new).
+    ///
+    /// [`clear`](Self::clear) resets the policy using [`EvictionPolicy::clear`], which returns it to its initial state
+    /// unless the policy keeps its parameters (as [`Random`](policy::Random) keeps its generator).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{policy::Random, MemoCache};
+    ///
+    /// let c = MemoCache::<u32, String, 4, Random>::with_policy(Random::new(42));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn with_policy(policy: P) -> Self {
         Self {
-            buffer: [const { KeyValueSlot::Empty }; SIZE],
-            cursor: 0,
+            raw: RawMemoCache {
+                buffer: [const { Slot::EMPTY }; SIZE],
+                states: [const { P::SLOT_INIT }; SIZE],
+                policy,
+                len: 0,
+                marker: PhantomData,
+            },
         }
     }
 
@@ -112,21 +210,6 @@ where
         SIZE
     }
 
-    /// Replace slot under cursor and shift cursor position. Returns a reference to the replaced slot value.
-    #[cfg_attr(feature = "inline-more", inline)]
-    fn replace_and_shift(&mut self, k: K, v: V) -> &V {
-        // SAFETY: The cursor value is assumed to be correct.
-        let s = unsafe { self.buffer.get_unchecked_mut(self.cursor) };
-
-        *s = KeyValueSlot::Used((k, v));
-
-        // Move the cursor over the buffer elements sequentially, creating FIFO behavior.
-        self.cursor = (self.cursor + 1) % SIZE;
-
-        // SAFETY: The slot was filled with a key/value above.
-        unsafe { s.get_value().unwrap_unchecked() }
-    }
-
     /// Insert a key/value pair.
     ///
     /// # Examples
@@ -144,12 +227,7 @@ where
     /// ```
     #[cfg_attr(feature = "inline-more", inline)]
     pub fn insert(&mut self, k: K, v: V) {
-        match self.buffer.iter_mut().find(|e| e.is_key(&k)) {
-            Some(s) => s.update_value(v),
-            None => {
-                self.replace_and_shift(k, v);
-            }
-        }
+        self.raw.insert(k, v);
     }
 
     /// Returns `true` if the cache contains a value for the specified key.
@@ -173,7 +251,7 @@ where
         K: Borrow<Q>,
         Q: Eq + ?Sized,
     {
-        self.buffer.iter().any(|e| e.is_key(k))
+        self.raw.contains_key(k)
     }
 
     /// Lookup a cache entry by key.
@@ -197,10 +275,7 @@ where
         K: Borrow<Q>,
         Q: Eq + ?Sized,
     {
-        self.buffer
-            .iter()
-            .find(|e| e.is_key(k))
-            .map(|e| e.get_value().unwrap())
+        self.raw.get(k)
     }
 
     /// Lookup a cache entry by key (for mutation).
@@ -226,20 +301,7 @@ where
         K: Borrow<Q>,
         Q: Eq + ?Sized,
     {
-        self.buffer
-            .iter_mut()
-            .find(|e| e.is_key(k))
-            .map(|e| e.get_value_mut().unwrap())
-    }
-
-    /// Get the index for a given key, if found.
-    #[cfg_attr(feature = "inline-more", inline)]
-    fn get_key_index<Q>(&self, k: &Q) -> Option<usize>
-    where
-        K: Borrow<Q>,
-        Q: Eq + ?Sized,
-    {
-        self.buffer.iter().position(|e| e.is_key(k))
+        self.raw.get_mut(k)
     }
 
     /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
@@ -271,12 +333,7 @@ where
     where
         F: FnOnce(&K) -> V,
     {
-        if let Some(i) = self.get_key_index(k) {
-            // SAFETY: The key index was retrieved from a found key.
-            unsafe { self.buffer[i].get_value().unwrap_unchecked() }
-        } else {
-            self.replace_and_shift(k.clone(), f(k))
-        }
+        self.raw.get_or_insert_with(k, f)
     }
 
     /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
@@ -315,12 +372,7 @@ where
     where
         F: FnOnce(&K) -> Result<V, E>,
     {
-        if let Some(i) = self.get_key_index(k) {
-            // SAFETY: The key index was retrieved from a found key.
-            Ok(unsafe { self.buffer[i].get_value().unwrap_unchecked() })
-        } else {
-            f(k).map(|v| self.replace_and_shift(k.clone(), v))
-        }
+        self.raw.get_or_try_insert_with(k, f)
     }
 
     /// Clear the cache.
@@ -344,17 +396,24 @@ where
     ///
     #[cfg_attr(feature = "inline-more", inline)]
     pub fn clear(&mut self) {
-        self.buffer
-            .iter_mut()
-            .for_each(|e| *e = KeyValueSlot::Empty);
-        self.cursor = 0;
+        self.raw.clear();
+    }
+}
+
+impl<K, V, const SIZE: usize, P> Drop for MemoCache<K, V, SIZE, P>
+where
+    P: EvictionPolicy<K>,
+{
+    fn drop(&mut self) {
+        self.raw.drop_used();
     }
 }
 
-impl<K, V, const SIZE: usize> Default for MemoCache<K, V, SIZE>
+impl<K, V, const SIZE: usize, P> Default for MemoCache<K, V, SIZE, P>
 where
     K: Clone + Eq,
     V: Clone,
+    P: EvictionPolicy<K>,
 {
     fn default() -> Self {
         Self::new()
@@ -372,33 +431,34 @@ mod tests_internal {
         let c = MemoCache::<i32, i32, SIZE>::new();
 
         // Verify cache size.
-        assert_eq!(c.buffer.len(), SIZE);
+        assert_eq!(c.raw.buffer.len(), SIZE);
         assert_eq!(c.capacity(), SIZE);
 
         // All slots should be empty.
-        assert!(c.buffer.iter().all(|s| s == &KeyValueSlot::Empty));
+        assert_eq!(c.raw.len, 0);
+        assert!(c.raw.entries().is_empty());
     }
 
     #[test]
     fn test_cursor_state() {
         let mut c = MemoCache::<i32, i32, 2>::new();
 
-        assert_eq!(c.cursor, 0);
+        assert_eq!(c.raw.policy.cursor, 0);
 
         c.insert(1, 2);
 
-        assert_eq!(c.cursor, 1);
+        assert_eq!(c.raw.policy.cursor, 1);
 
         c.insert(3, 4);
 
-        assert_eq!(c.cursor, 0);
+        assert_eq!(c.raw.policy.cursor, 0);
 
         c.insert(5, 6);
 
-        assert_eq!(c.cursor, 1);
+        assert_eq!(c.raw.policy.cursor, 1);
 
         c.insert(7, 8);
 
-        assert_eq!(c.cursor, 0);
+        assert_eq!(c.raw.policy.cursor, 0);
     }
 }
@@ -0,0 +1,469 @@
+//! Thread-local memo cache, declared using [`thread_local_memo!`](crate::thread_local_memo).
+
+use crate::{CacheStats, EvictionPolicy, Fifo, MemoCache};
+use core::{
+    borrow::Borrow,
+    cell::{RefCell, RefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::thread::LocalKey;
+
+/// Declare a thread-local memo cache, see [`ThreadLocalMemo`].
+///
+/// The syntax is similar to [`std::thread_local!`], but the type is always a `ThreadLocalMemo` and there is no
+/// initializer. Multiple caches can be declared at once. Non-trivial capacity expressions must be put in braces (as
+/// for any const generic argument).
+///
+/// # Examples
+///
+/// ```
+/// use memo_cache::{policy::Lru, thread_local_memo};
+///
+/// const SIZE: usize = 8;
+///
+/// thread_local_memo! {
+///     static SQUARES: ThreadLocalMemo<u32, u64, 32>;
+///
+///     /// Cache with a non-default eviction policy.
+///     pub static NAMES: ThreadLocalMemo<u32, String, { 2 * SIZE }, Lru>;
+/// }
+///
+/// assert_eq!(SQUARES.get_or_insert_with(&3, |&k| u64::from(k * k)), 9);
+/// ```
+#[macro_export]
+macro_rules! thread_local_memo {
+    () => {};
+
+    (
+        $(#[$attr:meta])*
+        $vis:vis static $name:ident: ThreadLocalMemo<$k:ty, $v:ty, $size:tt $(, $p:ty)? $(,)?>;
+        $($rest:tt)*
+    ) => {
+        $(#[$attr])*
+        $vis static $name: $crate::ThreadLocalMemo<$k, $v, $size $(, $p)?> = {
+            static STATS: $crate::LocalStats = $crate::LocalStats::new();
+
+            ::std::thread_local! {
+                static LOCAL: $crate::LocalMemoCache<$k, $v, $size $(, $p)?> =
+                    const { $crate::LocalMemoCache::new(&STATS) };
+            }
+
+            $crate::ThreadLocalMemo::new(&LOCAL, &STATS)
+        };
+
+        $crate::thread_local_memo!($($rest)*);
+    };
+}
+
+/// A small, fixed-size, thread-local key/value cache with retention management.
+///
+/// Every thread owns a separate [`MemoCache`], so no locking is required, and threads never see each other's values.
+/// Caches are declared in a `static` item using the [`thread_local_memo!`](crate::thread_local_memo) macro, and each
+/// thread's cache is created on first use, and dropped when the thread exits. As references into the cache cannot
+/// outlive the access, lookups return cloned values. For other access, use [`with_cache`](Self::with_cache).
+///
+/// Lookup hits and misses are counted over all threads, see [`stats`](Self::stats).
+pub struct ThreadLocalMemo<K, V, const SIZE: usize, P = Fifo>
+where
+    K: 'static,
+    V: 'static,
+    P: EvictionPolicy<K> + 'static,
+{
+    local: &'static LocalKey<LocalMemoCache<K, V, SIZE, P>>,
+    stats: &'static LocalStats,
+}
+
+impl<K, V, const SIZE: usize, P> ThreadLocalMemo<K, V, SIZE, P>
+where
+    K: Clone + Eq + 'static,
+    V: Clone + 'static,
+    P: EvictionPolicy<K> + 'static,
+{
+    /// Create a new cache from its thread-local storage (used by [`thread_local_memo!`](crate::thread_local_memo)).
+    #[doc(hidden)]
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new(
+        local: &'static LocalKey<LocalMemoCache<K, V, SIZE, P>>,
+        stats: &'static LocalStats,
+    ) -> Self {
+        Self { local, stats }
+    }
+
+    /// Get the (fixed) capacity of the cache of each thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::thread_local_memo;
+    ///
+    /// thread_local_memo! {
+    ///     static CACHE: ThreadLocalMemo<u32, f32, 8>;
+    /// }
+    ///
+    /// assert_eq!(CACHE.capacity(), 8);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn capacity(&self) -> usize {
+        SIZE
+    }
+
+    /// Run `f` with direct access to the cache of the current thread.
+    ///
+    /// Accessing the same cache from within `f` (other than through its argument) panics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::thread_local_memo;
+    ///
+    /// thread_local_memo! {
+    ///     static CACHE: ThreadLocalMemo<u32, String, 4>;
+    /// }
+    ///
+    /// CACHE.insert(42, "The Answer".to_owned());
+    ///
+    /// // Borrow the value, instead of cloning it.
+    /// assert_eq!(CACHE.with_cache(|c| c.get(&42).map(String::len)), Some(10));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn with_cache<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut MemoCache<K, V, SIZE, P>) -> R,
+    {
+        self.local.with(|l| l.with_cache(f))
+    }
+
+    /// Count a lookup hit or miss.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn count(&self, hit: bool) {
+        let counter = if hit {
+            &self.stats.hits
+        } else {
+            &self.stats.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Insert a key/value pair in the cache of the current thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::thread_local_memo;
+    ///
+    /// thread_local_memo! {
+    ///     static CACHE: ThreadLocalMemo<u32, &str, 4>;
+    /// }
+    ///
+    /// assert_eq!(CACHE.get(&42), None);
+    ///
+    /// CACHE.insert(42, "The Answer");
+    ///
+    /// assert_eq!(CACHE.get(&42), Some("The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn insert(&self, k: K, v: V) {
+        self.with_cache(|c| c.insert(k, v));
+    }
+
+    /// Returns `true` if the cache of the current thread contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::thread_local_memo;
+    ///
+    /// thread_local_memo! {
+    ///     static CACHE: ThreadLocalMemo<u32, &str, 4>;
+    /// }
+    ///
+    /// assert_eq!(CACHE.contains_key(&42), false);
+    ///
+    /// CACHE.insert(42, "The Answer");
+    ///
+    /// assert_eq!(CACHE.contains_key(&42), true);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.with_cache(|c| c.contains_key(k))
+    }
+
+    /// Lookup a cache entry of the current thread by key, returning a clone of the value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::thread_local_memo;
+    ///
+    /// thread_local_memo! {
+    ///     static CACHE: ThreadLocalMemo<u32, &str, 4>;
+    /// }
+    ///
+    /// assert_eq!(CACHE.get(&42), None);
+    ///
+    /// CACHE.insert(42, "The Answer");
+    ///
+    /// assert_eq!(CACHE.get(&42), Some("The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let v = self.with_cache(|c| c.get(k).cloned());
+        self.count(v.is_some());
+        v
+    }
+
+    /// Get a value, or, if it does not exist in the cache of the current thread, insert it using the value computed by
+    /// `f`. Returns a clone of the found, or newly inserted value associated with the given key.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// The cache is not borrowed while `f` runs, so `f` may use the cache itself (e.g. for recursive memoization). If
+    /// `f` inserted a value for the same key, that value is kept and returned instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::thread_local_memo;
+    ///
+    /// thread_local_memo! {
+    ///     static CACHE: ThreadLocalMemo<u32, &str, 4>;
+    /// }
+    ///
+    /// assert_eq!(CACHE.get(&42), None);
+    ///
+    /// let v = CACHE.get_or_insert_with(&42, |_| "The Answer");
+    ///
+    /// assert_eq!(v, "The Answer");
+    /// assert_eq!(CACHE.get(&42), Some("The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_insert_with<F>(&self, k: &K, f: F) -> V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self.get(k) {
+            Some(v) => v,
+            None => {
+                let v = f(k);
+                self.with_cache(|c| c.get_or_insert_with(k, |_| v).clone())
+            }
+        }
+    }
+
+    /// Get a value, or, if it does not exist in the cache of the current thread, insert it using the value computed by
+    /// `f`. Returns a result with a clone of the found, or newly inserted value associated with the given key.
+    /// If `f` fails, the error is returned.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// The cache is not borrowed while `f` runs, so `f` may use the cache itself (e.g. for recursive memoization). If
+    /// `f` inserted a value for the same key, that value is kept and returned instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::thread_local_memo;
+    ///
+    /// thread_local_memo! {
+    ///     static CACHE: ThreadLocalMemo<u32, &str, 4>;
+    /// }
+    ///
+    /// let v = CACHE.get_or_try_insert_with(&42, |_| Ok::<_, &str>("The Answer"));
+    ///
+    /// assert_eq!(v, Ok("The Answer"));
+    ///
+    /// let v = CACHE.get_or_try_insert_with(&17, |_| Err("Dunno"));
+    ///
+    /// assert_eq!(v, Err("Dunno"));
+    /// assert_eq!(CACHE.get(&17), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_try_insert_with<F, E>(&self, k: &K, f: F) -> Result<V, E>
+    where
+        F: FnOnce(&K) -> Result<V, E>,
+    {
+        match self.get(k) {
+            Some(v) => Ok(v),
+            None => {
+                let v = f(k)?;
+                Ok(self.with_cache(|c| c.get_or_insert_with(k, |_| v).clone()))
+            }
+        }
+    }
+
+    /// Get the cache statistics, merged over all threads.
+    ///
+    /// Lookups by [`get`](Self::get), [`get_or_insert_with`](Self::get_or_insert_with) and
+    /// [`get_or_try_insert_with`](Self::get_or_try_insert_with) are counted, in any thread. The length is the total
+    /// number of values in the caches of all running threads, so it shows how well the per-thread capacity is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::thread_local_memo;
+    /// use std::thread;
+    ///
+    /// thread_local_memo! {
+    ///     static CACHE: ThreadLocalMemo<u32, u32, 4>;
+    /// }
+    ///
+    /// CACHE.get_or_insert_with(&1, |&k| k * 2);
+    ///
+    /// thread::spawn(|| {
+    ///     CACHE.get_or_insert_with(&1, |&k| k * 2);
+    ///     CACHE.get_or_insert_with(&1, |&k| k * 2);
+    /// })
+    /// .join()
+    /// .unwrap();
+    ///
+    /// let stats = CACHE.stats();
+    ///
+    /// assert_eq!((stats.hits, stats.misses), (1, 2));
+    /// assert_eq!(stats.len, 1); // The cache of the spawned thread was dropped.
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.stats.hits.load(Ordering::Relaxed),
+            misses: self.stats.misses.load(Ordering::Relaxed),
+            len: self.stats.len.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Clear the cache of the current thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::thread_local_memo;
+    ///
+    /// thread_local_memo! {
+    ///     static CACHE: ThreadLocalMemo<u32, &str, 4>;
+    /// }
+    ///
+    /// CACHE.insert(42, "The Answer");
+    /// CACHE.insert(17, "Dunno");
+    ///
+    /// CACHE.clear();
+    ///
+    /// assert_eq!(CACHE.contains_key(&42), false);
+    /// assert_eq!(CACHE.contains_key(&17), false);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn clear(&self) {
+        self.with_cache(|c| c.clear());
+    }
+}
+
+/// Statistics of a [`ThreadLocalMemo`], shared by all threads (used by [`thread_local_memo!`](crate::thread_local_memo)).
+#[doc(hidden)]
+pub struct LocalStats {
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    len: AtomicUsize,
+}
+
+impl LocalStats {
+    /// Create new, zeroed statistics.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        Self {
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            len: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Default for LocalStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cache of a single thread of a [`ThreadLocalMemo`] (used by [`thread_local_memo!`](crate::thread_local_memo)).
+///
+/// The length of the cache is tracked in the shared statistics, and removed from it when the thread exits.
+#[doc(hidden)]
+pub struct LocalMemoCache<K, V, const SIZE: usize, P = Fifo>
+where
+    P: EvictionPolicy<K>,
+{
+    cache: RefCell<MemoCache<K, V, SIZE, P>>,
+    stats: &'static LocalStats,
+}
+
+impl<K, V, const SIZE: usize, P> LocalMemoCache<K, V, SIZE, P>
+where
+    K: Clone + Eq,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    /// Create a new, empty cache, using the given shared statistics.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new(stats: &'static LocalStats) -> Self {
+        Self {
+            cache: RefCell::new(MemoCache::new()),
+            stats,
+        }
+    }
+
+    /// Run `f` with mutable access to the cache, keeping track of its length (also if `f` panics).
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn with_cache<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut MemoCache<K, V, SIZE, P>) -> R,
+    {
+        let cache = self.cache.borrow_mut();
+        let mut guard = TrackLen {
+            len: cache.raw.len,
+            cache,
+            stats: self.stats,
+        };
+
+        f(&mut guard.cache)
+    }
+}
+
+/// Guard updating the shared length with the change in length of the cache when dropped, i.e. when the access
+/// completes, or when a panic interrupts it.
+struct TrackLen<'a, K, V, const SIZE: usize, P>
+where
+    P: EvictionPolicy<K>,
+{
+    cache: RefMut<'a, MemoCache<K, V, SIZE, P>>,
+    stats: &'static LocalStats,
+    len: usize,
+}
+
+impl<K, V, const SIZE: usize, P> Drop for TrackLen<'_, K, V, SIZE, P>
+where
+    P: EvictionPolicy<K>,
+{
+    fn drop(&mut self) {
+        let len = self.cache.raw.len;
+
+        if len > self.len {
+            self.stats.len.fetch_add(len - self.len, Ordering::Relaxed);
+        } else {
+            self.stats.len.fetch_sub(self.len - len, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<K, V, const SIZE: usize, P> Drop for LocalMemoCache<K, V, SIZE, P>
+where
+    P: EvictionPolicy<K>,
+{
+    fn drop(&mut self) {
+        self.stats
+            .len
+            .fetch_sub(self.cache.get_mut().raw.len, Ordering::Relaxed);
+    }
+}
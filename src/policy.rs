@@ -0,0 +1,1220 @@
+//! Eviction policies, deciding which slot is replaced when the cache is full.
+//!
+//! Policies that record lookup hits make a cache using them `!Sync`, see [`EvictionPolicy::on_hit`].
+
+// Policy and slot state constants are initializers, copied into every new cache (interior mutability is intended).
+#![allow(clippy::declare_interior_mutable_const)]
+
+mod list;
+mod sketch;
+
+use crate::hash;
+use core::{cell::Cell, hash::Hash};
+use list::{Link, List, Node, NIL};
+use sketch::FrequencySketch;
+
+/// An eviction policy for a [`MemoCache`](crate::MemoCache).
+///
+/// A policy keeps a small amount of global state (the policy value itself), and an optional piece of per-slot state
+/// (`SlotState`) that is stored alongside the cache slots. The cache notifies the policy of slot hits and insertions,
+/// and asks it for a victim slot when a new key/value must be inserted into a full cache.
+///
+/// The provided policies can be created using their `new` constructors, so they can also be wrapped by other policies
+/// (delegating to the wrapped policy).
+pub trait EvictionPolicy<K> {
+    /// Per-slot state kept by the policy.
+    type SlotState;
+
+    /// Initial policy state.
+    const INIT: Self;
+
+    /// Initial per-slot state.
+    const SLOT_INIT: Self::SlotState;
+
+    /// Called when the key in the slot at `index` is looked up successfully.
+    ///
+    /// Hits are reported through a shared reference, because lookups like [`MemoCache::get`](crate::MemoCache::get) do
+    /// not take the cache mutably. Policies that record hits (e.g. [`Lru`]) use interior mutability (e.g. [`Cell`]) for
+    /// this, which makes a cache using them `!Sync`. Such a cache can still be shared between threads behind a mutex.
+    fn on_hit(&self, states: &[Self::SlotState], index: usize, key: &K);
+
+    /// Called after `key` was inserted into the slot at `index`, which either was empty or was chosen by `victim`.
+    fn on_insert(&mut self, states: &mut [Self::SlotState], index: usize, key: &K);
+
+    /// Select the slot to be replaced by `key`, which is about to be inserted. Only called when all slots are in use.
+    ///
+    /// The keys of all slots are available through `keys`, e.g. to compare the new key with the key of a victim.
+    fn victim<S: SlotKeys<K> + ?Sized>(
+        &mut self,
+        states: &mut [Self::SlotState],
+        key: &K,
+        keys: &S,
+    ) -> usize;
+
+    /// Called instead of `on_insert` when the slot at `index`, chosen by `victim`, keeps its key after all, e.g. when
+    /// [`TinyLfu`] rejects the new key. `key` is the key in the slot.
+    ///
+    /// Policies that move the victim out of their bookkeeping in `victim` put it back here, as far as possible in the
+    /// place it was taken from. The default does nothing, which suits policies that only pick a slot.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn cancel_victim(&mut self, states: &mut [Self::SlotState], index: usize, key: &K) {
+        let _ = (states, index, key);
+    }
+
+    /// Reset the policy state, called when the cache is cleared. The per-slot states are reset by the cache.
+    ///
+    /// The default replaces the policy with [`INIT`](Self::INIT), so parameters the policy was created with (see
+    /// [`MemoCache::with_policy`](crate::MemoCache::with_policy)) are lost. Policies with such parameters override this
+    /// to keep them, like [`Random`] keeps its generator.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn clear(&mut self)
+    where
+        Self: Sized,
+    {
+        *self = Self::INIT;
+    }
+}
+
+/// The keys of the slots of a full cache, by slot index (see [`EvictionPolicy::victim`]).
+pub trait SlotKeys<K> {
+    /// Get the key in the slot at `index`.
+    fn key(&self, index: usize) -> &K;
+}
+
+impl<K, V> SlotKeys<K> for [(K, V)] {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn key(&self, index: usize) -> &K {
+        &self[index].0
+    }
+}
+
+impl<K> SlotKeys<K> for [K] {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn key(&self, index: usize) -> &K {
+        &self[index]
+    }
+}
+
+/// First-in, first-out eviction (the default policy).
+///
+/// A cursor moves over the slots sequentially, always pointing to the oldest slot.
+#[derive(Clone, Debug)]
+pub struct Fifo {
+    pub(crate) cursor: usize,
+}
+
+impl Fifo {
+    /// Create a new policy.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        Self { cursor: 0 }
+    }
+}
+
+impl Default for Fifo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> EvictionPolicy<K> for Fifo {
+    type SlotState = ();
+
+    const INIT: Self = Self::new();
+    const SLOT_INIT: Self::SlotState = ();
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_hit(&self, _: &[Self::SlotState], _: usize, _: &K) {}
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_insert(&mut self, states: &mut [Self::SlotState], index: usize, _: &K) {
+        // Move the cursor over the buffer elements sequentially, creating FIFO behavior.
+        self.cursor = (index + 1) % states.len();
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn victim<S: SlotKeys<K> + ?Sized>(
+        &mut self,
+        _: &mut [Self::SlotState],
+        _: &K,
+        _: &S,
+    ) -> usize {
+        self.cursor
+    }
+}
+
+/// Least recently used eviction.
+///
+/// Every slot carries the tick of its last use (a lookup hit or an insertion), and the slot with the oldest tick is
+/// evicted.
+#[derive(Debug)]
+pub struct Lru {
+    tick: Cell<u64>,
+}
+
+impl Lru {
+    /// Create a new policy.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        Self { tick: Cell::new(0) }
+    }
+
+    /// Advance the tick and stamp the slot at `index` with it.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn touch(&self, states: &[Cell<u64>], index: usize) {
+        let t = self.tick.get() + 1;
+        self.tick.set(t);
+        states[index].set(t);
+    }
+}
+
+impl Default for Lru {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> EvictionPolicy<K> for Lru {
+    type SlotState = Cell<u64>;
+
+    const INIT: Self = Self::new();
+    const SLOT_INIT: Self::SlotState = Cell::new(0);
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_hit(&self, states: &[Self::SlotState], index: usize, _: &K) {
+        self.touch(states, index);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_insert(&mut self, states: &mut [Self::SlotState], index: usize, _: &K) {
+        self.touch(states, index);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn victim<S: SlotKeys<K> + ?Sized>(
+        &mut self,
+        states: &mut [Self::SlotState],
+        _: &K,
+        _: &S,
+    ) -> usize {
+        states
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, t)| t.get())
+            .map_or(0, |(i, _)| i)
+    }
+}
+
+/// CLOCK (second chance) eviction.
+///
+/// Like [`Fifo`], a cursor moves over the slots sequentially, acting as the clock hand. Every slot carries a reference
+/// bit which is set on a lookup hit. When selecting a victim, the hand skips slots with the reference bit set, clearing
+/// the bit as it passes.
+#[derive(Debug)]
+pub struct Clock {
+    pub(crate) cursor: usize,
+}
+
+impl Clock {
+    /// Create a new policy.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        Self { cursor: 0 }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> EvictionPolicy<K> for Clock {
+    type SlotState = Cell<bool>;
+
+    const INIT: Self = Self::new();
+    const SLOT_INIT: Self::SlotState = Cell::new(false);
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_hit(&self, states: &[Self::SlotState], index: usize, _: &K) {
+        states[index].set(true);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_insert(&mut self, states: &mut [Self::SlotState], index: usize, _: &K) {
+        states[index].set(false);
+        self.cursor = (index + 1) % states.len();
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn victim<S: SlotKeys<K> + ?Sized>(
+        &mut self,
+        states: &mut [Self::SlotState],
+        _: &K,
+        _: &S,
+    ) -> usize {
+        // All reference bits are cleared within a single turn of the hand, so this terminates.
+        while states[self.cursor].replace(false) {
+            self.cursor = (self.cursor + 1) % states.len();
+        }
+
+        self.cursor
+    }
+}
+
+/// Per-slot state of the [`Sieve`] policy.
+#[derive(Debug)]
+pub struct SieveSlot {
+    visited: Cell<bool>,
+    link: Link,
+}
+
+impl Node for SieveSlot {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn link(&self) -> &Link {
+        &self.link
+    }
+}
+
+/// SIEVE eviction.
+///
+/// Slots are kept in insertion order in a queue, and carry a visited bit which is set on a lookup hit. A cursor (the
+/// hand) moves from the oldest towards the newest slot, skipping visited slots and clearing their bit as it passes. The
+/// first unvisited slot is evicted. Unlike [`Clock`], new entries are queued as the newest entries instead of taking
+/// over the position of the victim, so retained entries are not moved.
+#[derive(Debug)]
+pub struct Sieve {
+    queue: List,
+    cursor: usize,
+}
+
+impl Sieve {
+    /// Create a new policy.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        Self {
+            queue: List::INIT,
+            cursor: NIL,
+        }
+    }
+}
+
+impl Default for Sieve {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> EvictionPolicy<K> for Sieve {
+    type SlotState = SieveSlot;
+
+    const INIT: Self = Self::new();
+    const SLOT_INIT: Self::SlotState = SieveSlot {
+        visited: Cell::new(false),
+        link: Link::INIT,
+    };
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_hit(&self, states: &[Self::SlotState], index: usize, _: &K) {
+        states[index].visited.set(true);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_insert(&mut self, states: &mut [Self::SlotState], index: usize, _: &K) {
+        states[index].visited.set(false);
+        self.queue.push_front(states, index);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn victim<S: SlotKeys<K> + ?Sized>(
+        &mut self,
+        states: &mut [Self::SlotState],
+        _: &K,
+        _: &S,
+    ) -> usize {
+        let mut i = if self.cursor == NIL {
+            self.queue.back()
+        } else {
+            self.cursor
+        };
+
+        // All visited bits are cleared within a single pass of the hand, so this terminates.
+        while states[i].visited.replace(false) {
+            i = match self.queue.newer(states, i) {
+                NIL => self.queue.back(),
+                n => n,
+            };
+        }
+
+        self.cursor = self.queue.newer(states, i);
+        self.queue.remove(states, i);
+
+        i
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn cancel_victim(&mut self, states: &mut [Self::SlotState], index: usize, _: &K) {
+        // Requeue the slot where it was, and leave the hand pointing at it.
+        self.queue.insert_older(states, self.cursor, index);
+        self.cursor = index;
+    }
+}
+
+/// Per-slot state of the [`S3Fifo`] policy.
+#[derive(Debug)]
+pub struct S3FifoSlot {
+    freq: Cell<u8>,
+    fingerprint: u32,
+    link: Link,
+}
+
+impl Node for S3FifoSlot {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn link(&self) -> &Link {
+        &self.link
+    }
+}
+
+/// S3-FIFO eviction.
+///
+/// Slots are kept in one of two FIFO queues: a small probationary queue taking `SMALL_PERCENT` percent of the cache
+/// capacity, and a main queue taking the rest. New keys enter the small queue. When evicting from the small queue,
+/// keys that were hit move to the main queue, while keys that were not are evicted, and their fingerprints are
+/// remembered in a ghost set of `GHOST` entries. Keys found in the ghost set on insertion enter the main queue
+/// directly. The main queue evicts like [`Clock`], using a small saturating hit counter. This keeps one-hit wonders
+/// from flushing the cache.
+///
+/// Keys must be hashable, for the ghost set fingerprints.
+#[derive(Debug)]
+pub struct S3Fifo<const SMALL_PERCENT: usize = 10, const GHOST: usize = 32> {
+    small: List,
+    main: List,
+    ghost: [u32; GHOST],
+    ghost_cursor: usize,
+    small_victim: bool,
+}
+
+impl<const SMALL_PERCENT: usize, const GHOST: usize> S3Fifo<SMALL_PERCENT, GHOST> {
+    /// Maximum value of the per-slot hit counter.
+    const MAX_FREQ: u8 = 3;
+
+    /// Create a new policy.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        Self {
+            small: List::INIT,
+            main: List::INIT,
+            ghost: [0; GHOST],
+            ghost_cursor: 0,
+            small_victim: false,
+        }
+    }
+
+    /// Remember a fingerprint in the ghost set, overwriting the oldest one.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn remember(&mut self, fingerprint: u32) {
+        if GHOST > 0 {
+            self.ghost[self.ghost_cursor] = fingerprint;
+            self.ghost_cursor = (self.ghost_cursor + 1) % GHOST;
+        }
+    }
+
+    /// Remove the most recently remembered fingerprint from the ghost set.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn forget_newest(&mut self) {
+        if GHOST > 0 {
+            self.ghost_cursor = (self.ghost_cursor + GHOST - 1) % GHOST;
+            self.ghost[self.ghost_cursor] = 0;
+        }
+    }
+
+    /// Remove a fingerprint from the ghost set. Returns `true` if it was found.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn forget(&mut self, fingerprint: u32) -> bool {
+        match self.ghost.iter_mut().find(|g| **g == fingerprint) {
+            Some(g) => {
+                *g = 0;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<const SMALL_PERCENT: usize, const GHOST: usize> Default for S3Fifo<SMALL_PERCENT, GHOST> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, const SMALL_PERCENT: usize, const GHOST: usize> EvictionPolicy<K>
+    for S3Fifo<SMALL_PERCENT, GHOST>
+where
+    K: Hash,
+{
+    type SlotState = S3FifoSlot;
+
+    const INIT: Self = Self::new();
+    const SLOT_INIT: Self::SlotState = S3FifoSlot {
+        freq: Cell::new(0),
+        fingerprint: 0,
+        link: Link::INIT,
+    };
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_hit(&self, states: &[Self::SlotState], index: usize, _: &K) {
+        let f = &states[index].freq;
+        f.set((f.get() + 1).min(Self::MAX_FREQ));
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_insert(&mut self, states: &mut [Self::SlotState], index: usize, key: &K) {
+        let fingerprint = hash::fingerprint(key);
+        let main = self.forget(fingerprint);
+
+        let s = &mut states[index];
+        s.freq.set(0);
+        s.fingerprint = fingerprint;
+
+        if main {
+            self.main.push_front(states, index);
+        } else {
+            self.small.push_front(states, index);
+        }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn victim<S: SlotKeys<K> + ?Sized>(
+        &mut self,
+        states: &mut [Self::SlotState],
+        _: &K,
+        _: &S,
+    ) -> usize {
+        let small_target = (states.len() * SMALL_PERCENT / 100).max(1);
+
+        // Every pass either moves a key out of the small queue or decrements a hit counter, so this terminates.
+        loop {
+            if self.small.len() >= small_target || self.main.is_empty() {
+                let i = self.small.back();
+                self.small.remove(states, i);
+
+                if states[i].freq.get() > 0 {
+                    self.main.push_front(states, i);
+                } else {
+                    self.remember(states[i].fingerprint);
+                    self.small_victim = true;
+                    return i;
+                }
+            } else {
+                let i = self.main.back();
+                let f = &states[i].freq;
+
+                if f.get() > 0 {
+                    f.set(f.get() - 1);
+                    self.main.move_to_front(states, i);
+                } else {
+                    self.main.remove(states, i);
+                    self.small_victim = false;
+                    return i;
+                }
+            }
+        }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn cancel_victim(&mut self, states: &mut [Self::SlotState], index: usize, _: &K) {
+        // Keys moved to the main queue or aged on the way to the victim stay that way.
+        if self.small_victim {
+            self.forget_newest();
+            self.small.push_back(states, index);
+        } else {
+            self.main.push_back(states, index);
+        }
+    }
+}
+
+/// Least frequently used eviction.
+///
+/// Every slot carries a saturating use counter, which is incremented on a lookup hit. Every `AGING_PERIOD` hits and
+/// insertions, all counters are halved, so keys that were popular in the past eventually make room for keys that are
+/// popular now. The slot with the lowest counter is evicted, where ties are broken by scanning from the slot after the
+/// previous victim.
+#[derive(Debug)]
+pub struct Lfu<const AGING_PERIOD: usize = 256> {
+    accesses: Cell<usize>,
+    cursor: usize,
+}
+
+impl<const AGING_PERIOD: usize> Lfu<AGING_PERIOD> {
+    /// Create a new policy.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        Self {
+            accesses: Cell::new(0),
+            cursor: 0,
+        }
+    }
+
+    /// Count an access, and age all counters if the aging period has passed.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn access(&self, states: &[Cell<u8>]) {
+        let a = self.accesses.get() + 1;
+
+        if a >= AGING_PERIOD {
+            states.iter().for_each(|s| s.set(s.get() / 2));
+            self.accesses.set(0);
+        } else {
+            self.accesses.set(a);
+        }
+    }
+}
+
+impl<const AGING_PERIOD: usize> Default for Lfu<AGING_PERIOD> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, const AGING_PERIOD: usize> EvictionPolicy<K> for Lfu<AGING_PERIOD> {
+    type SlotState = Cell<u8>;
+
+    const INIT: Self = Self::new();
+    const SLOT_INIT: Self::SlotState = Cell::new(0);
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_hit(&self, states: &[Self::SlotState], index: usize, _: &K) {
+        let c = &states[index];
+        c.set(c.get().saturating_add(1));
+        self.access(states);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_insert(&mut self, states: &mut [Self::SlotState], index: usize, _: &K) {
+        states[index].set(1);
+        self.cursor = (index + 1) % states.len();
+        self.access(states);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn victim<S: SlotKeys<K> + ?Sized>(
+        &mut self,
+        states: &mut [Self::SlotState],
+        _: &K,
+        _: &S,
+    ) -> usize {
+        let (before, after) = states.split_at(self.cursor);
+
+        after
+            .iter()
+            .chain(before)
+            .enumerate()
+            .min_by_key(|(_, c)| c.get())
+            .map_or(0, |(i, _)| (self.cursor + i) % states.len())
+    }
+}
+
+/// TinyLFU admission, wrapping another eviction policy (FIFO by default).
+///
+/// All key accesses (lookup hits and insertions) are counted in a fixed-size count-min sketch with a doorkeeper. When
+/// the cache is full, the wrapped policy selects a victim, and the new key only replaces it if its estimated access
+/// frequency is higher than that of the victim. This keeps cold keys from displacing hot ones.
+///
+/// Because [`MemoCache::get_or_insert_with`](crate::MemoCache::get_or_insert_with) returns a reference to the stored
+/// value, a rejected key cannot simply be dropped. Instead, the last slot is reserved as an admission window: rejected
+/// keys are stored there, replacing the previously rejected key, and the rejected victim is reinserted into the wrapped
+/// policy. The wrapped policy only manages the other slots, so admission requires a cache of at least 2 slots. With a
+/// single slot, every new key replaces the previous one.
+///
+/// The sketch holds 4 rows of `16 * WORDS` 4-bit counters, and a doorkeeper of `64 * WORDS` bits absorbing the first
+/// access of every key. Counters are halved periodically to follow changing access patterns. Keys must be hashable.
+///
+/// # Examples
+///
+/// ```
+/// use memo_cache::{policy::{Lru, TinyLfu}, MemoCache};
+///
+/// let mut c = MemoCache::<u32, u32, 8, TinyLfu<Lru>>::new();
+///
+/// assert_eq!(c.get_or_insert_with(&42, |&k| k * 2), &84);
+/// ```
+#[derive(Debug)]
+pub struct TinyLfu<P = Fifo, const WORDS: usize = 8> {
+    inner: P,
+    sketch: FrequencySketch<WORDS>,
+}
+
+impl<P, const WORDS: usize> TinyLfu<P, WORDS> {
+    /// Create a new policy, wrapping the given policy.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new(inner: P) -> Self {
+        Self {
+            inner,
+            sketch: FrequencySketch::INIT,
+        }
+    }
+}
+
+impl<P, const WORDS: usize> Default for TinyLfu<P, WORDS>
+where
+    P: Default,
+{
+    fn default() -> Self {
+        Self::new(P::default())
+    }
+}
+
+impl<K, P, const WORDS: usize> EvictionPolicy<K> for TinyLfu<P, WORDS>
+where
+    K: Hash,
+    P: EvictionPolicy<K>,
+{
+    type SlotState = P::SlotState;
+
+    const INIT: Self = Self::new(P::INIT);
+    const SLOT_INIT: Self::SlotState = P::SLOT_INIT;
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_hit(&self, states: &[Self::SlotState], index: usize, key: &K) {
+        self.sketch.increment(hash::hash(key));
+
+        let window = states.len() - 1;
+
+        if index < window {
+            self.inner.on_hit(&states[..window], index, key);
+        }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_insert(&mut self, states: &mut [Self::SlotState], index: usize, key: &K) {
+        self.sketch.increment(hash::hash(key));
+
+        let window = states.len() - 1;
+
+        if index < window {
+            self.inner.on_insert(&mut states[..window], index, key);
+        }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn victim<S: SlotKeys<K> + ?Sized>(
+        &mut self,
+        states: &mut [Self::SlotState],
+        key: &K,
+        keys: &S,
+    ) -> usize {
+        let window = states.len() - 1;
+
+        if window == 0 {
+            return 0;
+        }
+
+        let states = &mut states[..window];
+        let i = self.inner.victim(states, key, keys);
+
+        if self.sketch.estimate(hash::hash(key)) > self.sketch.estimate(hash::hash(keys.key(i))) {
+            i
+        } else {
+            self.inner.cancel_victim(states, i, keys.key(i));
+            window
+        }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn cancel_victim(&mut self, states: &mut [Self::SlotState], index: usize, key: &K) {
+        let window = states.len() - 1;
+
+        if index < window {
+            self.inner.cancel_victim(&mut states[..window], index, key);
+        }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn clear(&mut self) {
+        self.inner.clear();
+        self.sketch = FrequencySketch::INIT;
+    }
+}
+
+/// Tag for the ghost list links of [`ArcSlot`].
+#[derive(Debug)]
+struct Ghost;
+
+/// Per-slot state of the [`Arc`] policy.
+///
+/// Besides the state of the resident key, every slot state holds a single ghost entry. ARC never remembers more ghost
+/// keys than the cache capacity, so this is all the ghost storage needed.
+#[derive(Debug)]
+pub struct ArcSlot {
+    frequent: Cell<bool>,
+    fingerprint: u32,
+    resident: Link,
+    ghost_fingerprint: u32,
+    ghost: Link,
+}
+
+impl Node for ArcSlot {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn link(&self) -> &Link {
+        &self.resident
+    }
+}
+
+impl Node<Ghost> for ArcSlot {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn link(&self) -> &Link {
+        &self.ghost
+    }
+}
+
+/// Adaptive replacement cache (ARC) eviction.
+///
+/// Resident keys are kept in two LRU lists: T1 for keys seen once recently, and T2 for keys that were hit since they
+/// were inserted. The fingerprints of keys evicted from T1 and T2 are remembered in the ghost lists B1 and B2. The
+/// target size of T1 adapts at runtime: a miss on a key found in B1 grows it (favoring recency), a miss on a key found
+/// in B2 shrinks it (favoring frequency). Keys found in either ghost list are inserted into T2.
+///
+/// All lists live in the fixed-size per-slot state array. Keys must be hashable, for the ghost list fingerprints.
+#[derive(Debug)]
+pub struct Arc {
+    t1: List,
+    t2: List,
+    b1: List<Ghost>,
+    b2: List<Ghost>,
+    free: List<Ghost>,
+    ghosts_used: usize,
+    target: usize,
+    promote: bool,
+}
+
+impl Arc {
+    /// Create a new policy.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        Self {
+            t1: List::INIT,
+            t2: List::INIT,
+            b1: List::INIT,
+            b2: List::INIT,
+            free: List::INIT,
+            ghosts_used: 0,
+            target: 0,
+            promote: false,
+        }
+    }
+
+    /// Remember a fingerprint in ghost list B1 or B2.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn remember(&mut self, states: &mut [ArcSlot], frequent: bool, fingerprint: u32) {
+        let g = match self.free.pop_back(states) {
+            NIL => {
+                self.ghosts_used += 1;
+                self.ghosts_used - 1
+            }
+            g => g,
+        };
+
+        states[g].ghost_fingerprint = fingerprint;
+
+        if frequent {
+            self.b2.push_front(states, g);
+        } else {
+            self.b1.push_front(states, g);
+        }
+    }
+
+    /// Drop the oldest entry of a ghost list.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn forget_oldest(&mut self, states: &mut [ArcSlot], frequent: bool) {
+        let g = if frequent {
+            self.b2.pop_back(states)
+        } else {
+            self.b1.pop_back(states)
+        };
+
+        if g != NIL {
+            self.free.push_front(states, g);
+        }
+    }
+
+    /// Evict the oldest key of T1 or T2 (depending on the target size of T1), and remember it in a ghost list.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn replace(&mut self, states: &mut [ArcSlot], in_b2: bool) -> usize {
+        let t1 = self.t1.len();
+        let frequent =
+            !(t1 > 0 && (t1 > self.target || (in_b2 && t1 == self.target)) || self.t2.is_empty());
+
+        let i = if frequent {
+            self.t2.pop_back(states)
+        } else {
+            self.t1.pop_back(states)
+        };
+
+        self.remember(states, frequent, states[i].fingerprint);
+
+        i
+    }
+}
+
+impl Default for Arc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> EvictionPolicy<K> for Arc
+where
+    K: Hash,
+{
+    type SlotState = ArcSlot;
+
+    const INIT: Self = Self::new();
+    const SLOT_INIT: Self::SlotState = ArcSlot {
+        frequent: Cell::new(false),
+        fingerprint: 0,
+        resident: Link::INIT,
+        ghost_fingerprint: 0,
+        ghost: Link::INIT,
+    };
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_hit(&self, states: &[Self::SlotState], index: usize, _: &K) {
+        if states[index].frequent.replace(true) {
+            self.t2.move_to_front(states, index);
+        } else {
+            self.t1.remove(states, index);
+            self.t2.push_front(states, index);
+        }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_insert(&mut self, states: &mut [Self::SlotState], index: usize, key: &K) {
+        let frequent = core::mem::take(&mut self.promote);
+
+        let s = &mut states[index];
+        s.frequent.set(frequent);
+        s.fingerprint = hash::fingerprint(key);
+
+        if frequent {
+            self.t2.push_front(states, index);
+        } else {
+            self.t1.push_front(states, index);
+        }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn victim<S: SlotKeys<K> + ?Sized>(
+        &mut self,
+        states: &mut [Self::SlotState],
+        key: &K,
+        _: &S,
+    ) -> usize {
+        let capacity = states.len();
+        let fingerprint = hash::fingerprint(key);
+        let (b1, b2) = (self.b1.len(), self.b2.len());
+
+        let g = self.b1.find(states, |s| s.ghost_fingerprint == fingerprint);
+
+        if g != NIL {
+            // Recently evicted from T1: grow the target size of T1.
+            self.target = (self.target + (b2 / b1).max(1)).min(capacity);
+            self.b1.remove(states, g);
+            self.free.push_front(states, g);
+            self.promote = true;
+
+            return self.replace(states, false);
+        }
+
+        let g = self.b2.find(states, |s| s.ghost_fingerprint == fingerprint);
+
+        if g != NIL {
+            // Recently evicted from T2: shrink the target size of T1.
+            self.target = self.target.saturating_sub((b1 / b2).max(1));
+            self.b2.remove(states, g);
+            self.free.push_front(states, g);
+            self.promote = true;
+
+            return self.replace(states, true);
+        }
+
+        if self.t1.len() + b1 >= capacity {
+            if self.t1.len() < capacity {
+                self.forget_oldest(states, false);
+                self.replace(states, false)
+            } else {
+                // T1 takes up the whole cache, evict its oldest key without remembering it.
+                self.t1.pop_back(states)
+            }
+        } else {
+            if capacity + b1 + b2 >= 2 * capacity {
+                self.forget_oldest(states, true);
+            }
+
+            self.replace(states, false)
+        }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn cancel_victim(&mut self, states: &mut [Self::SlotState], index: usize, _: &K) {
+        let frequent = states[index].frequent.get();
+        let (resident, ghosts) = if frequent {
+            (&self.t2, &self.b2)
+        } else {
+            (&self.t1, &self.b1)
+        };
+
+        // Drop the ghost entry `replace` made for the victim. Ghost hits of the new key and dropped ghosts stay.
+        let g = ghosts.front();
+
+        if g != NIL && states[g].ghost_fingerprint == states[index].fingerprint {
+            ghosts.remove(states, g);
+            self.free.push_front(states, g);
+        }
+
+        resident.push_back(states, index);
+        self.promote = false;
+    }
+}
+
+/// A small pseudo random number generator (SplitMix64), accepting any seed value.
+#[derive(Clone, Debug)]
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    #[cfg_attr(feature = "inline-more", inline)]
+    const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Get a random value in the range `[0, n)`.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+}
+
+/// Seed used by [`Random`] and [`TwoChoices`] when the cache is created with `new`.
+const DEFAULT_SEED: u64 = 0x5eed;
+
+/// Random eviction.
+///
+/// A uniformly random slot is evicted, driven by a small built-in pseudo random number generator. The generator is
+/// seeded through the constructor (see [`MemoCache::with_policy`](crate::MemoCache::with_policy)), so equal seeds give
+/// equal eviction sequences. Clearing the cache does not reseed the generator.
+#[derive(Clone, Debug)]
+pub struct Random {
+    rng: SplitMix64,
+}
+
+impl Random {
+    /// Create a new policy, using the given seed for the random number generator.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new(seed: u64) -> Self {
+        Self {
+            rng: SplitMix64::new(seed),
+        }
+    }
+}
+
+impl<K> EvictionPolicy<K> for Random {
+    type SlotState = ();
+
+    const INIT: Self = Self::new(DEFAULT_SEED);
+    const SLOT_INIT: Self::SlotState = ();
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_hit(&self, _: &[Self::SlotState], _: usize, _: &K) {}
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_insert(&mut self, _: &mut [Self::SlotState], _: usize, _: &K) {}
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn victim<S: SlotKeys<K> + ?Sized>(
+        &mut self,
+        states: &mut [Self::SlotState],
+        _: &K,
+        _: &S,
+    ) -> usize {
+        self.rng.below(states.len())
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn clear(&mut self) {}
+}
+
+/// Power-of-two-choices eviction.
+///
+/// Two slots are sampled at random, and the one that was used (hit or inserted) least recently is evicted. This
+/// approximates [`Lru`] at a constant eviction cost. The random number generator is seeded like for [`Random`].
+#[derive(Debug)]
+pub struct TwoChoices {
+    rng: SplitMix64,
+    tick: Cell<u64>,
+}
+
+impl TwoChoices {
+    /// Create a new policy, using the given seed for the random number generator.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new(seed: u64) -> Self {
+        Self {
+            rng: SplitMix64::new(seed),
+            tick: Cell::new(0),
+        }
+    }
+
+    /// Advance the tick and stamp the slot at `index` with it.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn touch(&self, states: &[Cell<u64>], index: usize) {
+        let t = self.tick.get() + 1;
+        self.tick.set(t);
+        states[index].set(t);
+    }
+}
+
+impl<K> EvictionPolicy<K> for TwoChoices {
+    type SlotState = Cell<u64>;
+
+    const INIT: Self = Self::new(DEFAULT_SEED);
+    const SLOT_INIT: Self::SlotState = Cell::new(0);
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_hit(&self, states: &[Self::SlotState], index: usize, _: &K) {
+        self.touch(states, index);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_insert(&mut self, states: &mut [Self::SlotState], index: usize, _: &K) {
+        self.touch(states, index);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn victim<S: SlotKeys<K> + ?Sized>(
+        &mut self,
+        states: &mut [Self::SlotState],
+        _: &K,
+        _: &S,
+    ) -> usize {
+        let a = self.rng.below(states.len());
+        let b = self.rng.below(states.len());
+
+        if states[a].get() <= states[b].get() {
+            a
+        } else {
+            b
+        }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn clear(&mut self) {
+        self.tick.set(0);
+    }
+}
+
+/// Per-slot state of the [`Slru`] policy.
+#[derive(Debug)]
+pub struct SlruSlot {
+    protected: Cell<bool>,
+    link: Link,
+}
+
+impl Node for SlruSlot {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn link(&self) -> &Link {
+        &self.link
+    }
+}
+
+/// Segmented LRU eviction.
+///
+/// Slots are kept in one of two LRU segments: a probationary segment, and a protected segment taking at most
+/// `PROTECTED_PERCENT` percent of the cache capacity. New keys enter the probationary segment, and move to the
+/// protected segment when hit. When the protected segment overflows, its least recently used key is demoted back to the
+/// probationary segment, rather than being evicted. Victims are taken from the probationary segment.
+#[derive(Debug)]
+pub struct Slru<const PROTECTED_PERCENT: usize = 80> {
+    probation: List,
+    protected: List,
+}
+
+impl<const PROTECTED_PERCENT: usize> Slru<PROTECTED_PERCENT> {
+    /// Create a new policy.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        Self {
+            probation: List::INIT,
+            protected: List::INIT,
+        }
+    }
+}
+
+impl<const PROTECTED_PERCENT: usize> Default for Slru<PROTECTED_PERCENT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, const PROTECTED_PERCENT: usize> EvictionPolicy<K> for Slru<PROTECTED_PERCENT> {
+    type SlotState = SlruSlot;
+
+    const INIT: Self = Self::new();
+    const SLOT_INIT: Self::SlotState = SlruSlot {
+        protected: Cell::new(false),
+        link: Link::INIT,
+    };
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_hit(&self, states: &[Self::SlotState], index: usize, _: &K) {
+        if states[index].protected.replace(true) {
+            self.protected.move_to_front(states, index);
+            return;
+        }
+
+        self.probation.remove(states, index);
+        self.protected.push_front(states, index);
+
+        if self.protected.len() > states.len() * PROTECTED_PERCENT / 100 {
+            let i = self.protected.pop_back(states);
+            states[i].protected.set(false);
+            self.probation.push_front(states, i);
+        }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn on_insert(&mut self, states: &mut [Self::SlotState], index: usize, _: &K) {
+        states[index].protected.set(false);
+        self.probation.push_front(states, index);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn victim<S: SlotKeys<K> + ?Sized>(
+        &mut self,
+        states: &mut [Self::SlotState],
+        _: &K,
+        _: &S,
+    ) -> usize {
+        match self.probation.pop_back(states) {
+            NIL => self.protected.pop_back(states),
+            i => i,
+        }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn cancel_victim(&mut self, states: &mut [Self::SlotState], index: usize, _: &K) {
+        if states[index].protected.get() {
+            self.protected.push_back(states, index);
+        } else {
+            self.probation.push_back(states, index);
+        }
+    }
+}
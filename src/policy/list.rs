@@ -0,0 +1,177 @@
+//! Intrusive, index-based doubly linked lists over cache slots.
+
+use core::{cell::Cell, marker::PhantomData};
+
+/// Marker for the absence of a slot index.
+pub(crate) const NIL: usize = usize::MAX;
+
+/// List links of a single slot.
+#[derive(Debug)]
+pub(crate) struct Link {
+    /// Neighbour towards the head (newer).
+    prev: Cell<usize>,
+    /// Neighbour towards the tail (older).
+    next: Cell<usize>,
+}
+
+impl Link {
+    pub(crate) const INIT: Self = Self {
+        prev: Cell::new(NIL),
+        next: Cell::new(NIL),
+    };
+}
+
+/// Per-slot state containing list links. Per-slot states with multiple links implement this for multiple tags `T`.
+pub(crate) trait Node<T = ()> {
+    fn link(&self) -> &Link;
+}
+
+/// A doubly linked list of slot indices, using the slot links tagged `T`. Slots are pushed at the head, so the tail
+/// holds the oldest slot.
+#[derive(Debug)]
+pub(crate) struct List<T = ()> {
+    head: Cell<usize>,
+    tail: Cell<usize>,
+    len: Cell<usize>,
+    tag: PhantomData<T>,
+}
+
+impl<T> List<T> {
+    pub(crate) const INIT: Self = Self {
+        head: Cell::new(NIL),
+        tail: Cell::new(NIL),
+        len: Cell::new(0),
+        tag: PhantomData,
+    };
+
+    /// Get the number of slots in the list.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    /// Get the newest slot, or `NIL` if the list is empty.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn front(&self) -> usize {
+        self.head.get()
+    }
+
+    /// Get the oldest slot, or `NIL` if the list is empty.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn back(&self) -> usize {
+        self.tail.get()
+    }
+
+    /// Returns `true` if the list is empty.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len.get() == 0
+    }
+
+    /// Get the slot pushed right after slot `i`, or `NIL` if `i` is the head.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn newer<N: Node<T>>(&self, nodes: &[N], i: usize) -> usize {
+        Node::<T>::link(&nodes[i]).prev.get()
+    }
+
+    /// Push slot `i` at the head of the list. The slot must not be in any list.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn push_front<N: Node<T>>(&self, nodes: &[N], i: usize) {
+        let head = self.head.get();
+        let link = Node::<T>::link(&nodes[i]);
+
+        link.prev.set(NIL);
+        link.next.set(head);
+
+        if head == NIL {
+            self.tail.set(i);
+        } else {
+            Node::<T>::link(&nodes[head]).prev.set(i);
+        }
+
+        self.head.set(i);
+        self.len.set(self.len.get() + 1);
+    }
+
+    /// Insert slot `i` right after slot `newer` (i.e. as the next older slot), or at the head if `newer` is `NIL`. The
+    /// slot must not be in any list, and `newer` must be in this list.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn insert_older<N: Node<T>>(&self, nodes: &[N], newer: usize, i: usize) {
+        if newer == NIL {
+            self.push_front(nodes, i);
+            return;
+        }
+
+        let older = Node::<T>::link(&nodes[newer]).next.replace(i);
+        let link = Node::<T>::link(&nodes[i]);
+
+        link.prev.set(newer);
+        link.next.set(older);
+
+        if older == NIL {
+            self.tail.set(i);
+        } else {
+            Node::<T>::link(&nodes[older]).prev.set(i);
+        }
+
+        self.len.set(self.len.get() + 1);
+    }
+
+    /// Push slot `i` at the tail of the list, as the oldest slot. The slot must not be in any list.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn push_back<N: Node<T>>(&self, nodes: &[N], i: usize) {
+        self.insert_older(nodes, self.back(), i);
+    }
+
+    /// Remove slot `i` from the list. The slot must be in this list.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn remove<N: Node<T>>(&self, nodes: &[N], i: usize) {
+        let link = Node::<T>::link(&nodes[i]);
+        let (prev, next) = (link.prev.replace(NIL), link.next.replace(NIL));
+
+        if prev == NIL {
+            self.head.set(next);
+        } else {
+            Node::<T>::link(&nodes[prev]).next.set(next);
+        }
+
+        if next == NIL {
+            self.tail.set(prev);
+        } else {
+            Node::<T>::link(&nodes[next]).prev.set(prev);
+        }
+
+        self.len.set(self.len.get() - 1);
+    }
+
+    /// Find the oldest slot matching `f`, or `NIL` if there is none.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn find<N: Node<T>>(&self, nodes: &[N], f: impl Fn(&N) -> bool) -> usize {
+        let mut i = self.back();
+
+        while i != NIL && !f(&nodes[i]) {
+            i = self.newer(nodes, i);
+        }
+
+        i
+    }
+
+    /// Remove and return the oldest slot, or `NIL` if the list is empty.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn pop_back<N: Node<T>>(&self, nodes: &[N]) -> usize {
+        let i = self.back();
+
+        if i != NIL {
+            self.remove(nodes, i);
+        }
+
+        i
+    }
+
+    /// Move slot `i` to the head of the list. The slot must be in this list.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn move_to_front<N: Node<T>>(&self, nodes: &[N], i: usize) {
+        self.remove(nodes, i);
+        self.push_front(nodes, i);
+    }
+}
@@ -0,0 +1,164 @@
+//! Count-min frequency sketch with a doorkeeper, used for TinyLFU admission.
+
+use core::cell::Cell;
+
+/// Number of rows (hash functions) of the sketch.
+const ROWS: usize = 4;
+
+/// Number of 4-bit counters packed into a word.
+const COUNTERS_PER_WORD: usize = 16;
+
+/// Maximum counter value.
+const MAX_COUNT: u64 = 0xf;
+
+/// Mask for halving all packed counters at once (clearing the bit shifted in from the neighbouring counter).
+const HALVE_MASK: u64 = 0x7777_7777_7777_7777;
+
+/// Approximate access frequency counter of a fixed size.
+///
+/// Every row holds `16 * WORDS` saturating 4-bit counters, and a doorkeeper bloom filter of `64 * WORDS` bits absorbs
+/// the first access of a key so one-off keys do not pollute the counters. After a sample of `10 * 16 * WORDS` counted
+/// accesses all counters are halved and the doorkeeper is cleared, so the sketch follows changing access patterns.
+#[derive(Debug)]
+pub(crate) struct FrequencySketch<const WORDS: usize> {
+    rows: [[Cell<u64>; WORDS]; ROWS],
+    doorkeeper: [Cell<u64>; WORDS],
+    additions: Cell<usize>,
+}
+
+impl<const WORDS: usize> FrequencySketch<WORDS> {
+    pub(crate) const INIT: Self = Self {
+        rows: [const { [const { Cell::new(0) }; WORDS] }; ROWS],
+        doorkeeper: [const { Cell::new(0) }; WORDS],
+        additions: Cell::new(0),
+    };
+
+    /// Number of counted accesses after which the sketch is aged.
+    const SAMPLE_SIZE: usize = 10 * COUNTERS_PER_WORD * WORDS;
+
+    /// Get the word and bit shift of the counter for `hash` in row `r`.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn counter(hash: u64, r: usize) -> (usize, u32) {
+        let c = (hash >> (r * 16)) as usize % (COUNTERS_PER_WORD * WORDS);
+        (c / COUNTERS_PER_WORD, (c % COUNTERS_PER_WORD) as u32 * 4)
+    }
+
+    /// Get the word and bit mask of the doorkeeper bits for `hash`.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn doorkeeper_bits(hash: u64) -> [(usize, u64); 2] {
+        [hash >> 8, hash >> 40].map(|h| {
+            let b = h as usize % (64 * WORDS);
+            (b / 64, 1 << (b % 64))
+        })
+    }
+
+    /// Returns `true` if the doorkeeper has seen `hash` before.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn doorkeeper_contains(&self, hash: u64) -> bool {
+        Self::doorkeeper_bits(hash)
+            .iter()
+            .all(|&(w, m)| self.doorkeeper[w].get() & m != 0)
+    }
+
+    /// Count an access to the key with the given hash.
+    pub(crate) fn increment(&self, hash: u64) {
+        if WORDS == 0 {
+            return;
+        }
+
+        if self.doorkeeper_contains(hash) {
+            for (r, row) in self.rows.iter().enumerate() {
+                let (w, shift) = Self::counter(hash, r);
+                let word = row[w].get();
+
+                if (word >> shift) & MAX_COUNT < MAX_COUNT {
+                    row[w].set(word + (1 << shift));
+                }
+            }
+        } else {
+            for (w, m) in Self::doorkeeper_bits(hash) {
+                self.doorkeeper[w].set(self.doorkeeper[w].get() | m);
+            }
+        }
+
+        let a = self.additions.get() + 1;
+
+        if a >= Self::SAMPLE_SIZE {
+            self.age();
+        } else {
+            self.additions.set(a);
+        }
+    }
+
+    /// Estimate the access frequency of the key with the given hash.
+    pub(crate) fn estimate(&self, hash: u64) -> u64 {
+        if WORDS == 0 {
+            return 0;
+        }
+
+        let count = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(r, row)| {
+                let (w, shift) = Self::counter(hash, r);
+                (row[w].get() >> shift) & MAX_COUNT
+            })
+            .min()
+            .unwrap_or(0);
+
+        count + self.doorkeeper_contains(hash) as u64
+    }
+
+    /// Halve all counters and clear the doorkeeper.
+    fn age(&self) {
+        self.rows
+            .iter()
+            .flatten()
+            .for_each(|w| w.set((w.get() >> 1) & HALVE_MASK));
+        self.doorkeeper.iter().for_each(|w| w.set(0));
+        self.additions.set(0);
+    }
+}
+
+#[cfg(test)]
+mod tests_internal {
+    use super::*;
+
+    #[test]
+    fn test_doorkeeper() {
+        let s = FrequencySketch::<4>::INIT;
+
+        assert_eq!(s.estimate(42), 0);
+
+        // The first access is absorbed by the doorkeeper.
+        s.increment(42);
+
+        assert_eq!(s.estimate(42), 1);
+        assert!(s.rows.iter().flatten().all(|w| w.get() == 0));
+
+        s.increment(42);
+
+        assert_eq!(s.estimate(42), 2);
+    }
+
+    #[test]
+    fn test_saturation_and_aging() {
+        let s = FrequencySketch::<4>::INIT;
+        let h = 0x0123_4567_89ab_cdef;
+
+        for _ in 0..100 {
+            s.increment(h);
+        }
+
+        assert_eq!(s.estimate(h), MAX_COUNT + 1);
+
+        // Fill up the sample, which triggers aging.
+        for i in s.additions.get()..FrequencySketch::<4>::SAMPLE_SIZE {
+            s.increment((i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        }
+
+        assert_eq!(s.additions.get(), 0);
+        assert!(s.estimate(h) <= MAX_COUNT / 2);
+    }
+}
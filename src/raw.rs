@@ -0,0 +1,623 @@
+//! Cache logic shared by all slot storage types (inline arrays, boxed slices, borrowed slices and split key/value
+//! arrays).
+
+use crate::{Equivalent, EvictionPolicy, Slot, SlotKeys};
+use core::{
+    borrow::Borrow,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ptr, slice,
+};
+
+/// Contiguous storage of cache slots or slot states.
+pub(crate) trait Storage {
+    /// Stored item type.
+    type Item;
+
+    /// Get the stored items.
+    fn items(&self) -> &[Self::Item];
+
+    /// Get the stored items (for mutation).
+    fn items_mut(&mut self) -> &mut [Self::Item];
+}
+
+impl<T, const N: usize> Storage for [T; N] {
+    type Item = T;
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn items(&self) -> &[T] {
+        self
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn items_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Storage for alloc::boxed::Box<[T]> {
+    type Item = T;
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn items(&self) -> &[T] {
+        self
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn items_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T> Storage for &mut [T] {
+    type Item = T;
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn items(&self) -> &[T] {
+        self
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn items_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+/// Storage of a given number of empty slot states, for policies without slot state (which need no memory).
+pub(crate) struct UnitStates(pub(crate) usize);
+
+impl Storage for UnitStates {
+    type Item = ();
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn items(&self) -> &[()] {
+        // SAFETY: Zero-sized items need no backing memory, so a dangling (aligned) pointer is valid for any length.
+        unsafe { slice::from_raw_parts(ptr::NonNull::dangling().as_ptr(), self.0) }
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn items_mut(&mut self) -> &mut [()] {
+        // SAFETY: Zero-sized items need no backing memory, so a dangling (aligned) pointer is valid for any length.
+        unsafe { slice::from_raw_parts_mut(ptr::NonNull::dangling().as_ptr(), self.0) }
+    }
+}
+
+/// Storage of key/value slots, in a specific memory layout.
+///
+/// Slots are either initialized or empty, which is tracked by the cache (the storage has no occupancy flags). Indices
+/// are bounds checked against the capacity, but not against the number of initialized slots.
+pub(crate) trait SlotStorage<K, V> {
+    /// View of the keys of the initialized slots, as handed to eviction policies.
+    type Keys: SlotKeys<K> + ?Sized;
+
+    /// Get the number of slots.
+    fn capacity(&self) -> usize;
+
+    /// Write a key/value pair into the empty slot at index `i`.
+    fn write(&mut self, i: usize, k: K, v: V);
+
+    /// Get the keys of the first `len` slots.
+    ///
+    /// # Safety
+    ///
+    /// The first `len` slots must be initialized.
+    unsafe fn keys<'a>(&'a self, len: usize) -> &'a Self::Keys
+    where
+        K: 'a,
+        V: 'a;
+
+    /// Get the index of the slot with a key equivalent to `k` among the first `len` slots, if found.
+    ///
+    /// # Safety
+    ///
+    /// The first `len` slots must be initialized.
+    unsafe fn position<Q>(&self, len: usize, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized;
+
+    /// Get the key of the slot at index `i`.
+    ///
+    /// # Safety
+    ///
+    /// The slot must be initialized.
+    unsafe fn key<'a>(&'a self, i: usize) -> &'a K
+    where
+        V: 'a;
+
+    /// Get the value of the slot at index `i`.
+    ///
+    /// # Safety
+    ///
+    /// The slot must be initialized.
+    unsafe fn value<'a>(&'a self, i: usize) -> &'a V
+    where
+        K: 'a;
+
+    /// Get the value of the slot at index `i` (for mutation).
+    ///
+    /// # Safety
+    ///
+    /// The slot must be initialized.
+    unsafe fn value_mut<'a>(&'a mut self, i: usize) -> &'a mut V
+    where
+        K: 'a;
+
+    /// Replace the key/value pair in the slot at index `i`, dropping the previous pair.
+    ///
+    /// # Safety
+    ///
+    /// The slot must be initialized.
+    unsafe fn replace(&mut self, i: usize, k: K, v: V);
+
+    /// Drop the key/value pairs of the first `len` slots.
+    ///
+    /// # Safety
+    ///
+    /// The first `len` slots must be initialized, and are empty afterwards.
+    unsafe fn drop_first(&mut self, len: usize);
+}
+
+impl<K, V, B> SlotStorage<K, V> for B
+where
+    B: Storage<Item = Slot<K, V>>,
+{
+    type Keys = [(K, V)];
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn capacity(&self) -> usize {
+        self.items().len()
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn write(&mut self, i: usize, k: K, v: V) {
+        self.items_mut()[i].0.write((k, v));
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn keys<'a>(&'a self, len: usize) -> &'a [(K, V)]
+    where
+        K: 'a,
+        V: 'a,
+    {
+        // SAFETY: Slots are transparent wrappers of key/value pairs, of which the first `len` are initialized.
+        slice::from_raw_parts(self.items().as_ptr().cast(), len)
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn position<Q>(&self, len: usize, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.keys(len).iter().position(|(key, _)| k.equivalent(key))
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn key<'a>(&'a self, i: usize) -> &'a K
+    where
+        V: 'a,
+    {
+        &self.items()[i].0.assume_init_ref().0
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn value<'a>(&'a self, i: usize) -> &'a V
+    where
+        K: 'a,
+    {
+        &self.items()[i].0.assume_init_ref().1
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn value_mut<'a>(&'a mut self, i: usize) -> &'a mut V
+    where
+        K: 'a,
+    {
+        &mut self.items_mut()[i].0.assume_init_mut().1
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn replace(&mut self, i: usize, k: K, v: V) {
+        *self.items_mut()[i].0.assume_init_mut() = (k, v);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn drop_first(&mut self, len: usize) {
+        ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+            self.items_mut().as_mut_ptr().cast::<(K, V)>(),
+            len,
+        ));
+    }
+}
+
+/// Structure-of-arrays slot storage, keeping keys apart from values.
+pub(crate) struct SplitSlots<K, V, const SIZE: usize> {
+    keys: [MaybeUninit<K>; SIZE],
+    values: [MaybeUninit<V>; SIZE],
+}
+
+impl<K, V, const SIZE: usize> SplitSlots<K, V, SIZE> {
+    /// Empty slots.
+    pub(crate) const EMPTY: Self = Self {
+        keys: [const { MaybeUninit::uninit() }; SIZE],
+        values: [const { MaybeUninit::uninit() }; SIZE],
+    };
+}
+
+impl<K, V, const SIZE: usize> SlotStorage<K, V> for SplitSlots<K, V, SIZE> {
+    type Keys = [K];
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn capacity(&self) -> usize {
+        SIZE
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn write(&mut self, i: usize, k: K, v: V) {
+        self.keys[i].write(k);
+        self.values[i].write(v);
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn keys<'a>(&'a self, len: usize) -> &'a [K]
+    where
+        K: 'a,
+        V: 'a,
+    {
+        // SAFETY: The first `len` keys are initialized.
+        slice::from_raw_parts(self.keys.as_ptr().cast(), len)
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn position<Q>(&self, len: usize, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        // Only the key array is scanned, so values are not pulled through the CPU cache.
+        self.keys(len).iter().position(|key| k.equivalent(key))
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn key<'a>(&'a self, i: usize) -> &'a K
+    where
+        V: 'a,
+    {
+        self.keys[i].assume_init_ref()
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn value<'a>(&'a self, i: usize) -> &'a V
+    where
+        K: 'a,
+    {
+        self.values[i].assume_init_ref()
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn value_mut<'a>(&'a mut self, i: usize) -> &'a mut V
+    where
+        K: 'a,
+    {
+        self.values[i].assume_init_mut()
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn replace(&mut self, i: usize, k: K, v: V) {
+        *self.keys[i].assume_init_mut() = k;
+        *self.values[i].assume_init_mut() = v;
+    }
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    unsafe fn drop_first(&mut self, len: usize) {
+        // Values are dropped even if dropping a key panics (and vice versa), as the drop of a slice continues with the
+        // remaining elements on unwind.
+        ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+            self.keys.as_mut_ptr().cast::<K>(),
+            len,
+        ));
+        ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+            self.values.as_mut_ptr().cast::<V>(),
+            len,
+        ));
+    }
+}
+
+/// Cache logic over inline slot (state) arrays.
+pub(crate) type RawArrayMemoCache<K, V, const SIZE: usize, P> =
+    RawMemoCache<K, V, [Slot<K, V>; SIZE], [<P as EvictionPolicy<K>>::SlotState; SIZE], P>;
+
+/// Cache logic over boxed slot (state) slices.
+#[cfg(feature = "alloc")]
+pub(crate) type RawBoxedMemoCache<K, V, P> = RawMemoCache<
+    K,
+    V,
+    alloc::boxed::Box<[Slot<K, V>]>,
+    alloc::boxed::Box<[<P as EvictionPolicy<K>>::SlotState]>,
+    P,
+>;
+
+/// Cache logic over borrowed slots, for policies without slot state.
+pub(crate) type RawBorrowedMemoCache<'a, K, V, P> =
+    RawMemoCache<K, V, &'a mut [Slot<K, V>], UnitStates, P>;
+
+/// Cache logic over split key and value arrays.
+pub(crate) type RawSplitMemoCache<K, V, const SIZE: usize, P> =
+    RawMemoCache<K, V, SplitSlots<K, V, SIZE>, [<P as EvictionPolicy<K>>::SlotState; SIZE], P>;
+
+/// Key/value cache logic over slot storage `B`, with slot state storage `S` for eviction policy `P`.
+///
+/// Slots are filled front to back and are never emptied individually, so the first `len` slots are the used ones. The
+/// slot and slot state storage must be of equal length. Dropping the used slots is left to the owner (see
+/// [`drop_used`](Self::drop_used)), as the slot type itself has no drop glue.
+pub(crate) struct RawMemoCache<K, V, B, S, P> {
+    pub(crate) buffer: B,
+    pub(crate) states: S,
+    pub(crate) policy: P,
+    pub(crate) len: usize,
+    pub(crate) marker: PhantomData<(K, V)>,
+}
+
+#[cfg(test)]
+impl<K, V, B, S, P> RawMemoCache<K, V, B, S, P>
+where
+    B: Storage<Item = Slot<K, V>>,
+{
+    /// Get the key/value pairs of the used slots.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn entries(&self) -> &[(K, V)] {
+        // SAFETY: The first `len` slots are initialized.
+        unsafe { self.buffer.keys(self.len) }
+    }
+}
+
+impl<K, V, B, S, P> RawMemoCache<K, V, B, S, P>
+where
+    B: SlotStorage<K, V>,
+    S: Storage<Item = P::SlotState>,
+    P: EvictionPolicy<K>,
+{
+    /// Get the capacity of the cache.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Get the key of the used slot at index `i`.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn key(&self, i: usize) -> &K {
+        assert!(i < self.len);
+
+        // SAFETY: The first `len` slots are initialized.
+        unsafe { self.buffer.key(i) }
+    }
+
+    /// Replace the next empty slot or, if the cache is full, the slot selected by the eviction policy, and notify the
+    /// policy. Returns the index of the replaced slot.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn replace_and_shift(&mut self, k: K, v: V) -> usize {
+        // The policy may already have updated its state for the victim when the new key is hashed or the replaced pair
+        // is dropped, either of which may panic. Empty the cache in that case, as it cannot be rolled back.
+        let guard = ResetOnUnwind(self);
+        let this = &mut *guard.0;
+        let states = this.states.items_mut();
+
+        // Slots are filled front to back, so the policy only has to select victims when the cache is full.
+        let i = if this.len < this.buffer.capacity() {
+            this.buffer.write(this.len, k, v);
+            this.len += 1;
+            this.len - 1
+        } else {
+            // SAFETY: The cache is full, so all slots are initialized.
+            let i = this
+                .policy
+                .victim(states, &k, unsafe { this.buffer.keys(this.len) });
+
+            assert!(i < this.len);
+
+            // SAFETY: The cache is full, so all slots are initialized (the index is checked above).
+            unsafe { this.buffer.replace(i, k, v) };
+            i
+        };
+
+        // SAFETY: The slot was just written.
+        this.policy
+            .on_insert(states, i, unsafe { this.buffer.key(i) });
+
+        mem::forget(guard);
+        i
+    }
+
+    /// Get the value of the used slot at index `i`.
+    ///
+    /// # Safety
+    ///
+    /// The slot index must be below the number of used slots.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) unsafe fn value_unchecked(&self, i: usize) -> &V {
+        self.buffer.value(i)
+    }
+
+    /// Report a hit on the used slot at index `i` to the policy.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn touch(&self, i: usize) {
+        self.policy.on_hit(self.states.items(), i, self.key(i));
+    }
+
+    /// Report a hit on the used slot at index `i` to the policy, and get its value.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn hit(&self, i: usize) -> &V {
+        self.touch(i);
+
+        // SAFETY: The slot index was checked by `touch`.
+        unsafe { self.buffer.value(i) }
+    }
+
+    /// Report a hit on the used slot at index `i` to the policy, and get its value (for mutation).
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn hit_mut(&mut self, i: usize) -> &mut V {
+        self.touch(i);
+
+        // SAFETY: The slot index was checked by `touch`.
+        unsafe { self.buffer.value_mut(i) }
+    }
+
+    /// Get the index for a given key, if found.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn get_key_index<Q>(&self, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        // SAFETY: The first `len` slots are initialized.
+        unsafe { self.buffer.position(self.len, k) }
+    }
+
+    /// Get the index for a given key, if found, only comparing keys of the given candidate slots.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn get_key_index_among<Q>(
+        &self,
+        k: &Q,
+        mut candidates: impl Iterator<Item = usize>,
+    ) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        candidates.find(|&i| k.equivalent(self.key(i)))
+    }
+
+    /// Insert a key/value pair.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn insert(&mut self, k: K, v: V)
+    where
+        K: Eq,
+    {
+        match self.get_key_index(&k) {
+            Some(i) => *self.hit_mut(i) = v,
+            None => {
+                self.replace_and_shift(k, v);
+            }
+        }
+    }
+
+    /// Returns `true` if the cache contains a value for the specified key.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.get_key_index(k).is_some()
+    }
+
+    /// Lookup a cache entry by key.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.get_key_index(k).map(|i| self.hit(i))
+    }
+
+    /// Lookup a cache entry by key (for mutation).
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.get_key_index(k).map(|i| self.hit_mut(i))
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn get_or_insert_with<F>(&mut self, k: &K, f: F) -> &V
+    where
+        K: Clone + Eq,
+        F: FnOnce(&K) -> V,
+    {
+        let i = match self.get_key_index(k) {
+            Some(i) => {
+                self.touch(i);
+                i
+            }
+            None => self.replace_and_shift(k.clone(), f(k)),
+        };
+
+        // SAFETY: The key index was retrieved from a found key, or the slot was just filled.
+        unsafe { self.value_unchecked(i) }
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn get_or_try_insert_with<F, E>(&mut self, k: &K, f: F) -> Result<&V, E>
+    where
+        K: Clone + Eq,
+        F: FnOnce(&K) -> Result<V, E>,
+    {
+        let i = match self.get_key_index(k) {
+            Some(i) => {
+                self.touch(i);
+                i
+            }
+            None => self.replace_and_shift(k.clone(), f(k)?),
+        };
+
+        // SAFETY: The key index was retrieved from a found key, or the slot was just filled.
+        Ok(unsafe { self.value_unchecked(i) })
+    }
+
+    /// Clear the cache.
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub(crate) fn clear(&mut self) {
+        // Reset the policy first, so it is consistent with the empty slots even if dropping a pair panics.
+        self.reset_policy();
+        self.drop_used();
+    }
+
+    /// Reset the eviction policy and all slot states.
+    fn reset_policy(&mut self) {
+        self.states
+            .items_mut()
+            .iter_mut()
+            .for_each(|s| *s = P::SLOT_INIT);
+        self.policy.clear();
+    }
+
+    /// Drop the key/value pairs of all used slots, marking all slots as empty.
+    pub(crate) fn drop_used(&mut self) {
+        let len = self.len;
+
+        // Mark the slots as empty first, so a panicking drop leaks the remaining pairs instead of dropping them twice.
+        self.len = 0;
+
+        // SAFETY: The first `len` slots are initialized, and no longer reachable.
+        unsafe { self.buffer.drop_first(len) };
+    }
+}
+
+/// Guard emptying the cache when dropped, i.e. when a panic interrupts an update (unless forgotten on completion).
+///
+/// The key/value pairs of the used slots are leaked rather than dropped, as dropping them may panic again while
+/// unwinding.
+struct ResetOnUnwind<'a, K, V, B, S, P>(&'a mut RawMemoCache<K, V, B, S, P>)
+where
+    B: SlotStorage<K, V>,
+    S: Storage<Item = P::SlotState>,
+    P: EvictionPolicy<K>;
+
+impl<K, V, B, S, P> Drop for ResetOnUnwind<'_, K, V, B, S, P>
+where
+    B: SlotStorage<K, V>,
+    S: Storage<Item = P::SlotState>,
+    P: EvictionPolicy<K>,
+{
+    fn drop(&mut self) {
+        self.0.len = 0;
+        self.0.reset_policy();
+    }
+}
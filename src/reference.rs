@@ -0,0 +1,296 @@
+//! Memo cache over caller-provided (borrowed) slot storage.
+
+use crate::{
+    raw::{RawBorrowedMemoCache, RawMemoCache, UnitStates},
+    EvictionPolicy, Fifo, Slot,
+};
+use core::{borrow::Borrow, marker::PhantomData};
+
+/// A small, fixed-size key/value cache with retention management, operating on caller-provided slots.
+///
+/// The cache does not own its slots, but borrows them from the caller. This allows placing the slots at a specific
+/// location (e.g. a `static` buffer, or a specific linker section in firmware). The slots are const-constructible, see
+/// [`Slot`]. The API and eviction semantics are identical to [`MemoCache`](crate::MemoCache).
+///
+/// Only eviction policies without per-slot state are supported, as only the slots themselves are provided (i.e.
+/// [`Fifo`] and [`Random`](crate::policy::Random)).
+///
+/// When the cache is dropped, the used slots are dropped as well, and the (empty) slots can be reused for a new cache.
+///
+/// # Examples
+///
+/// ```
+/// use memo_cache::{MemoCacheRef, Slot};
+///
+/// static mut SLOTS: [Slot<u32, u32>; 64] = [Slot::EMPTY; 64];
+///
+/// // SAFETY: The slots are only borrowed here, once.
+/// let mut c = MemoCacheRef::<u32, u32>::new(unsafe { &mut *core::ptr::addr_of_mut!(SLOTS) });
+///
+/// assert_eq!(c.capacity(), 64);
+/// assert_eq!(c.get_or_insert_with(&42, |&k| k * 2), &84);
+/// ```
+pub struct MemoCacheRef<'a, K, V, P = Fifo>
+where
+    P: EvictionPolicy<K, SlotState = ()>,
+{
+    raw: RawBorrowedMemoCache<'a, K, V, P>,
+}
+
+impl<'a, K, V, P> MemoCacheRef<'a, K, V, P>
+where
+    K: Clone + Eq,
+    V: Clone,
+    P: EvictionPolicy<K, SlotState = ()>,
+{
+    /// Create a new cache operating on the given slots. Any previous slot contents are discarded (without dropping).
+    ///
+    /// # Panics
+    ///
+    /// Panics if no slots are given.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{MemoCacheRef, Slot};
+    ///
+    /// let mut slots = [Slot::EMPTY; 4];
+    /// let c = MemoCacheRef::<u32, String>::new(&mut slots);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new(slots: &'a mut [Slot<K, V>]) -> Self {
+        Self::with_policy(slots, P::INIT)
+    }
+
+    /// Create a new cache operating on the given slots, using the given eviction policy state (see
+    /// [`MemoCache::with_policy`](crate::MemoCache::with_policy)). Any previous slot contents are discarded (without
+    /// dropping).
+    ///
+    /// # Panics
+    ///
+    /// Panics if no slots are given.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{policy::Random, MemoCacheRef, Slot};
+    ///
+    /// let mut slots = [Slot::EMPTY; 4];
+    /// let c = MemoCacheRef::<u32, String, Random>::with_policy(&mut slots, Random::new(42));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn with_policy(slots: &'a mut [Slot<K, V>], policy: P) -> Self {
+        assert!(!slots.is_empty(), "cache capacity must be non-zero");
+
+        Self {
+            raw: RawMemoCache {
+                states: UnitStates(slots.len()),
+                buffer: slots,
+                policy,
+                len: 0,
+                marker: PhantomData,
+            },
+        }
+    }
+
+    /// Get the (fixed) capacity of the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{MemoCacheRef, Slot};
+    ///
+    /// let mut slots = [Slot::EMPTY; 8];
+    /// let c = MemoCacheRef::<u32, String>::new(&mut slots);
+    ///
+    /// assert_eq!(c.capacity(), 8);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn capacity(&self) -> usize {
+        self.raw.capacity()
+    }
+
+    /// Insert a key/value pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{MemoCacheRef, Slot};
+    ///
+    /// let mut slots = [Slot::EMPTY; 4];
+    /// let mut c = MemoCacheRef::<u32, &str>::new(&mut slots);
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn insert(&mut self, k: K, v: V) {
+        self.raw.insert(k, v);
+    }
+
+    /// Returns `true` if the cache contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{MemoCacheRef, Slot};
+    ///
+    /// let mut slots = [Slot::EMPTY; 4];
+    /// let mut c = MemoCacheRef::<u32, &str>::new(&mut slots);
+    ///
+    /// assert_eq!(c.contains_key(&42), false);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.contains_key(&42), true);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.raw.contains_key(k)
+    }
+
+    /// Lookup a cache entry by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{MemoCacheRef, Slot};
+    ///
+    /// let mut slots = [Slot::EMPTY; 4];
+    /// let mut c = MemoCacheRef::<u32, &str>::new(&mut slots);
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.raw.get(k)
+    }
+
+    /// Lookup a cache entry by key (for mutation).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{MemoCacheRef, Slot};
+    ///
+    /// let mut slots = [Slot::EMPTY; 4];
+    /// let mut c = MemoCacheRef::<u32, &str>::new(&mut slots);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// if let Some(v) = c.get_mut(&42) {
+    ///     *v = "Another Answer";
+    /// }
+    ///
+    /// assert_eq!(c.get(&42), Some(&"Another Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.raw.get_mut(k)
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a reference to the found, or newly inserted value associated with the given key.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{MemoCacheRef, Slot};
+    ///
+    /// let mut slots = [Slot::EMPTY; 4];
+    /// let mut c = MemoCacheRef::<u32, &str>::new(&mut slots);
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// let v = c.get_or_insert_with(&42, |_| "The Answer");
+    ///
+    /// assert_eq!(v, &"The Answer");
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_insert_with<F>(&mut self, k: &K, f: F) -> &V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        self.raw.get_or_insert_with(k, f)
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a result with a reference to the found, or newly inserted value associated with the given key.
+    /// If `f` fails, the error is returned.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{MemoCacheRef, Slot};
+    ///
+    /// let mut slots = [Slot::EMPTY; 4];
+    /// let mut c = MemoCacheRef::<u32, &str>::new(&mut slots);
+    ///
+    /// let v = c.get_or_try_insert_with(&42, |_| Ok::<_, &str>("The Answer"));
+    ///
+    /// assert_eq!(v, Ok(&"The Answer"));
+    ///
+    /// let v = c.get_or_try_insert_with(&17, |_| Err("Dunno"));
+    ///
+    /// assert_eq!(v, Err("Dunno"));
+    /// assert_eq!(c.get(&17), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_try_insert_with<F, E>(&mut self, k: &K, f: F) -> Result<&V, E>
+    where
+        F: FnOnce(&K) -> Result<V, E>,
+    {
+        self.raw.get_or_try_insert_with(k, f)
+    }
+
+    /// Clear the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{MemoCacheRef, Slot};
+    ///
+    /// let mut slots = [Slot::EMPTY; 4];
+    /// let mut c = MemoCacheRef::<u32, &str>::new(&mut slots);
+    ///
+    /// c.insert(42, "The Answer");
+    /// c.clear();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn clear(&mut self) {
+        self.raw.clear();
+    }
+}
+
+impl<K, V, P> Drop for MemoCacheRef<'_, K, V, P>
+where
+    P: EvictionPolicy<K, SlotState = ()>,
+{
+    fn drop(&mut self) {
+        self.raw.drop_used();
+    }
+}
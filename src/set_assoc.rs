@@ -0,0 +1,316 @@
+//! Set-associative memo cache, for larger capacities.
+
+use crate::{hash, EvictionPolicy, Fifo, MemoCache};
+use core::{borrow::Borrow, hash::Hash};
+
+/// A fixed-size, set-associative key/value cache with retention management.
+///
+/// The cache is divided into `SETS` independent sets of `WAYS` slots each. The hash of a key selects the single set it
+/// can be stored in, and each set is a small [`MemoCache`] with its own eviction policy state (FIFO by default, see the
+/// [`policy`](crate::policy) module for alternatives, e.g. LRU). The cost of a lookup is therefore proportional to
+/// `WAYS` instead of the total capacity, while memory usage stays fixed.
+///
+/// Because keys are evicted per set, a key may be evicted while other sets still have empty slots.
+///
+/// The number of sets must be non-zero, which is checked at compile time:
+///
+/// ```compile_fail
+/// use memo_cache::SetAssocMemoCache;
+///
+/// let c = SetAssocMemoCache::<u32, String, 0, 8>::new();
+/// ```
+///
+/// The API is identical to [`MemoCache`], except that keys must implement [`Hash`].
+pub struct SetAssocMemoCache<K, V, const SETS: usize, const WAYS: usize, P = Fifo>
+where
+    P: EvictionPolicy<K>,
+{
+    sets: [MemoCache<K, V, WAYS, P>; SETS],
+}
+
+impl<K, V, const SETS: usize, const WAYS: usize, P> SetAssocMemoCache<K, V, SETS, WAYS, P>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    /// Create a new cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SetAssocMemoCache;
+    ///
+    /// let c = SetAssocMemoCache::<u32, String, 64, 8>::new();
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        const { assert!(SETS > 0, "number of sets must be non-zero") };
+
+        Self {
+            sets: [const { MemoCache::new() }; SETS],
+        }
+    }
+
+    /// Create a new cache, using the eviction policy state returned by `f` for every set, given the set index (see
+    /// [`MemoCache::with_policy`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{policy::Random, SetAssocMemoCache};
+    ///
+    /// // Seed every set differently.
+    /// let c = SetAssocMemoCache::<u32, String, 64, 8, Random>::with_policy(|i| Random::new(42 + i as u64));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn with_policy<F>(mut f: F) -> Self
+    where
+        F: FnMut(usize) -> P,
+    {
+        const { assert!(SETS > 0, "number of sets must be non-zero") };
+
+        Self {
+            sets: core::array::from_fn(|i| MemoCache::with_policy(f(i))),
+        }
+    }
+
+    /// Get the (fixed) capacity of the cache, i.e. the number of sets times the number of ways.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SetAssocMemoCache;
+    ///
+    /// let c = SetAssocMemoCache::<u32, String, 64, 8>::new();
+    ///
+    /// assert_eq!(c.capacity(), 512);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn capacity(&self) -> usize {
+        SETS * WAYS
+    }
+
+    /// Get the set a key maps to.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn set_index<Q: Hash + ?Sized>(k: &Q) -> usize {
+        hash::index(k, SETS)
+    }
+
+    /// Insert a key/value pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SetAssocMemoCache;
+    ///
+    /// let mut c = SetAssocMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn insert(&mut self, k: K, v: V) {
+        self.sets[Self::set_index(&k)].insert(k, v);
+    }
+
+    /// Returns `true` if the cache contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SetAssocMemoCache;
+    ///
+    /// let mut c = SetAssocMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// assert_eq!(c.contains_key(&42), false);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.contains_key(&42), true);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.sets[Self::set_index(k)].contains_key(k)
+    }
+
+    /// Lookup a cache entry by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SetAssocMemoCache;
+    ///
+    /// let mut c = SetAssocMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.sets[Self::set_index(k)].get(k)
+    }
+
+    /// Lookup a cache entry by key (for mutation).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SetAssocMemoCache;
+    ///
+    /// let mut c = SetAssocMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// if let Some(v) = c.get_mut(&42) {
+    ///     *v = "Another Answer";
+    /// }
+    ///
+    /// assert_eq!(c.get(&42), Some(&"Another Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.sets[Self::set_index(k)].get_mut(k)
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a reference to the found, or newly inserted value associated with the given key.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SetAssocMemoCache;
+    ///
+    /// let mut c = SetAssocMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// let v = c.get_or_insert_with(&42, |_| "The Answer");
+    ///
+    /// assert_eq!(v, &"The Answer");
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_insert_with<F>(&mut self, k: &K, f: F) -> &V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        self.sets[Self::set_index(k)].get_or_insert_with(k, f)
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a result with a reference to the found, or newly inserted value associated with the given key.
+    /// If `f` fails, the error is returned.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SetAssocMemoCache;
+    ///
+    /// let mut c = SetAssocMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// let v = c.get_or_try_insert_with(&42, |_| Ok::<_, &str>("The Answer"));
+    ///
+    /// assert_eq!(v, Ok(&"The Answer"));
+    ///
+    /// let v = c.get_or_try_insert_with(&17, |_| Err("Dunno"));
+    ///
+    /// assert_eq!(v, Err("Dunno"));
+    /// assert_eq!(c.get(&17), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_try_insert_with<F, E>(&mut self, k: &K, f: F) -> Result<&V, E>
+    where
+        F: FnOnce(&K) -> Result<V, E>,
+    {
+        self.sets[Self::set_index(k)].get_or_try_insert_with(k, f)
+    }
+
+    /// Clear the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SetAssocMemoCache;
+    ///
+    /// let mut c = SetAssocMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// c.insert(42, "The Answer");
+    /// c.clear();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn clear(&mut self) {
+        self.sets.iter_mut().for_each(MemoCache::clear);
+    }
+}
+
+impl<K, V, const SETS: usize, const WAYS: usize, P> Default
+    for SetAssocMemoCache<K, V, SETS, WAYS, P>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests_internal {
+    use super::*;
+
+    #[test]
+    fn test_sets_used() {
+        let mut c = SetAssocMemoCache::<u32, u32, 8, 4>::new();
+
+        (0..32).for_each(|k| c.insert(k, k));
+
+        // With a decent hash, every set receives some keys.
+        assert!(c.sets.iter().all(|s| s.raw.len > 0));
+    }
+
+    #[test]
+    fn test_set_local_eviction() {
+        let mut c = SetAssocMemoCache::<u32, u32, 8, 2>::new();
+
+        // Three keys in the same set: the first one is evicted, even though other sets are empty.
+        let mut keys = (0..).filter(|k| SetAssocMemoCache::<u32, u32, 8, 2>::set_index(k) == 3);
+        let [a, b, d] = [(); 3].map(|_| keys.next().unwrap());
+
+        c.insert(a, 1);
+        c.insert(b, 2);
+        c.insert(d, 3);
+
+        assert!(!c.contains_key(&a));
+        assert!(c.contains_key(&b));
+        assert!(c.contains_key(&d));
+        assert!(c
+            .sets
+            .iter()
+            .enumerate()
+            .all(|(i, s)| (i == 3) == (s.raw.len > 0)));
+    }
+}
@@ -0,0 +1,419 @@
+//! Sharded thread-safe memo cache, for parallel use.
+
+use crate::{hash, CacheStats, EvictionPolicy, Fifo, SyncMemoCache};
+use core::{borrow::Borrow, future::Future, hash::Hash};
+
+/// A fixed-size, thread-safe key/value cache with retention management, divided into independently locked shards.
+///
+/// The hash of a key selects the single shard it can be stored in, and each shard is a [`SyncMemoCache`] of `SIZE`
+/// slots with its own lock and eviction policy state (FIFO by default, see the [`policy`](crate::policy) module for
+/// alternatives). Threads accessing keys in different shards therefore do not contend for the same lock.
+///
+/// Locks are striped over the shards: an operation only holds the lock of the shard of its key, and statistics and
+/// clearing visit the shards one lock at a time, so no operation ever holds more than one lock. More shards reduce
+/// contention, at the cost of keys being evicted per shard, i.e. a key may be evicted while other shards still have
+/// empty slots.
+///
+/// A cache without shards cannot hold any key, so `SHARDS` must be at least one (a single shard amounts to a
+/// [`SyncMemoCache`]). A cache in a `static` without shards fails to compile:
+///
+/// ```compile_fail
+/// use memo_cache::ShardedMemoCache;
+///
+/// static CACHE: ShardedMemoCache<u64, f64, 0, 32> = ShardedMemoCache::new();
+/// ```
+///
+/// The API is identical to [`SyncMemoCache`], except that keys must implement [`Hash`].
+pub struct ShardedMemoCache<K, V, const SHARDS: usize, const SIZE: usize, P = Fifo>
+where
+    P: EvictionPolicy<K>,
+{
+    shards: [SyncMemoCache<K, V, SIZE, P>; SHARDS],
+}
+
+impl<K, V, const SHARDS: usize, const SIZE: usize, P> ShardedMemoCache<K, V, SHARDS, SIZE, P>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    /// Create a new cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::ShardedMemoCache;
+    ///
+    /// static CACHE: ShardedMemoCache<u32, String, 8, 16> = ShardedMemoCache::new();
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        const { assert!(SHARDS > 0, "number of shards must be non-zero") };
+
+        Self {
+            shards: [const { SyncMemoCache::new() }; SHARDS],
+        }
+    }
+
+    /// Create a new cache, using the eviction policy state returned by `f` for every shard, given the shard index (see
+    /// [`SyncMemoCache::with_policy`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{policy::Random, ShardedMemoCache};
+    ///
+    /// // Shards seeded alike evict in lockstep, so mix the shard index into the seed.
+    /// let c = ShardedMemoCache::<u64, f64, 4, 32, Random>::with_policy(|shard| Random::new(0x5eed ^ shard as u64));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn with_policy<F>(mut f: F) -> Self
+    where
+        F: FnMut(usize) -> P,
+    {
+        const { assert!(SHARDS > 0, "number of shards must be non-zero") };
+
+        Self {
+            shards: core::array::from_fn(|i| SyncMemoCache::with_policy(f(i))),
+        }
+    }
+
+    /// Get the (fixed) capacity of the cache, i.e. the number of shards times the shard size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::ShardedMemoCache;
+    ///
+    /// let c = ShardedMemoCache::<u32, String, 8, 16>::new();
+    ///
+    /// assert_eq!(c.capacity(), 128);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn capacity(&self) -> usize {
+        SHARDS * SIZE
+    }
+
+    /// Get the shard a key maps to.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn shard<Q: Hash + ?Sized>(&self, k: &Q) -> &SyncMemoCache<K, V, SIZE, P> {
+        &self.shards[hash::index(k, SHARDS)]
+    }
+
+    /// Insert a key/value pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::ShardedMemoCache;
+    ///
+    /// let c = ShardedMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some("The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn insert(&self, k: K, v: V) {
+        self.shard(&k).insert(k, v);
+    }
+
+    /// Returns `true` if the cache contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::ShardedMemoCache;
+    ///
+    /// let c = ShardedMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// assert_eq!(c.contains_key(&42), false);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.contains_key(&42), true);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.shard(k).contains_key(k)
+    }
+
+    /// Lookup a cache entry by key, returning a clone of the value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::ShardedMemoCache;
+    ///
+    /// let c = ShardedMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some("The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.shard(k).get(k)
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a clone of the found, or newly inserted value associated with the given key.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// The shard is not locked while `f` runs (see [`SyncMemoCache::get_or_insert_with`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::ShardedMemoCache;
+    ///
+    /// let c = ShardedMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// let v = c.get_or_insert_with(&42, |_| "The Answer");
+    ///
+    /// assert_eq!(v, "The Answer");
+    /// assert_eq!(c.get(&42), Some("The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_insert_with<F>(&self, k: &K, f: F) -> V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        self.shard(k).get_or_insert_with(k, f)
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a result with a clone of the found, or newly inserted value associated with the given key.
+    /// If `f` fails, the error is returned.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// The shard is not locked while `f` runs (see [`SyncMemoCache::get_or_try_insert_with`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::ShardedMemoCache;
+    ///
+    /// let c = ShardedMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// let v = c.get_or_try_insert_with(&42, |_| Ok::<_, &str>("The Answer"));
+    ///
+    /// assert_eq!(v, Ok("The Answer"));
+    ///
+    /// let v = c.get_or_try_insert_with(&17, |_| Err("Dunno"));
+    ///
+    /// assert_eq!(v, Err("Dunno"));
+    /// assert_eq!(c.get(&17), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_try_insert_with<F, E>(&self, k: &K, f: F) -> Result<V, E>
+    where
+        F: FnOnce(&K) -> Result<V, E>,
+        E: Clone + Send + Sync + 'static,
+    {
+        self.shard(k).get_or_try_insert_with(k, f)
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by the future returned
+    /// by `f`. Returns a clone of the found, or newly inserted value associated with the given key.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// See [`SyncMemoCache::get_or_insert_with_async`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::ShardedMemoCache;
+    ///
+    /// let c = ShardedMemoCache::<u32, String, 4, 2>::new();
+    ///
+    /// let v = futures::executor::block_on(c.get_or_insert_with_async(&42, |&k| async move { k.to_string() }));
+    ///
+    /// assert_eq!(v, "42");
+    /// assert_eq!(c.get(&42), Some("42".to_owned()));
+    /// ```
+    pub async fn get_or_insert_with_async<F, Fut>(&self, k: &K, f: F) -> V
+    where
+        F: FnOnce(&K) -> Fut,
+        Fut: Future<Output = V>,
+    {
+        self.shard(k).get_or_insert_with_async(k, f).await
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by the future returned
+    /// by `f`. Returns a result with a clone of the found, or newly inserted value associated with the given key.
+    /// If the future fails, the error is returned.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// See [`SyncMemoCache::get_or_try_insert_with_async`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::executor::block_on;
+    /// use memo_cache::ShardedMemoCache;
+    ///
+    /// let c = ShardedMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// let v = block_on(c.get_or_try_insert_with_async(&42, |_| async { Ok::<_, &str>("The Answer") }));
+    ///
+    /// assert_eq!(v, Ok("The Answer"));
+    ///
+    /// let v = block_on(c.get_or_try_insert_with_async(&17, |_| async { Err("Dunno") }));
+    ///
+    /// assert_eq!(v, Err("Dunno"));
+    /// assert_eq!(c.get(&17), None);
+    /// ```
+    pub async fn get_or_try_insert_with_async<F, Fut, E>(&self, k: &K, f: F) -> Result<V, E>
+    where
+        F: FnOnce(&K) -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+        E: Clone + Send + Sync + 'static,
+    {
+        self.shard(k).get_or_try_insert_with_async(k, f).await
+    }
+
+    /// Get the usage statistics of the cache, summed over all shards.
+    ///
+    /// The shards are locked one at a time, so the result is not a snapshot when other threads use the cache meanwhile.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{CacheStats, ShardedMemoCache};
+    ///
+    /// let c = ShardedMemoCache::<u32, u32, 4, 2>::new();
+    ///
+    /// for k in [1, 2, 1, 3] {
+    ///     c.get_or_insert_with(&k, |&k| k * 2);
+    /// }
+    ///
+    /// assert_eq!(c.stats(), CacheStats { hits: 1, misses: 3, len: 3 });
+    /// ```
+    pub fn stats(&self) -> CacheStats {
+        self.shards.iter().map(SyncMemoCache::stats).sum()
+    }
+
+    /// Clear the cache.
+    ///
+    /// The shards are cleared one at a time, so values inserted by other threads meanwhile may be retained.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::ShardedMemoCache;
+    ///
+    /// let c = ShardedMemoCache::<u32, &str, 4, 2>::new();
+    ///
+    /// c.insert(42, "The Answer");
+    /// c.clear();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn clear(&self) {
+        self.shards.iter().for_each(SyncMemoCache::clear);
+    }
+}
+
+impl<K, V, const SHARDS: usize, const SIZE: usize, P> Default
+    for ShardedMemoCache<K, V, SHARDS, SIZE, P>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests_internal {
+    use super::*;
+    use std::{sync::Barrier, thread, vec::Vec};
+
+    /// Get `n` keys that map to the given shard.
+    fn shard_keys<const SHARDS: usize>(shard: usize, n: usize) -> Vec<u32> {
+        (0..)
+            .filter(|k| hash::index(k, SHARDS) == shard)
+            .take(n)
+            .collect()
+    }
+
+    #[test]
+    fn test_lock_striping() {
+        let c = ShardedMemoCache::<u32, u32, 4, 4>::new();
+        let [a, b] = [0, 1].map(|shard| shard_keys::<4>(shard, 1)[0]);
+
+        // While the shard of one key is locked, keys of other shards remain accessible.
+        let guard = c.shards[0].lock();
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                assert_eq!(c.get_or_insert_with(&b, |&k| k + 1), b + 1);
+                assert!(c.contains_key(&b));
+            })
+            .join()
+            .unwrap();
+        });
+
+        assert!(!guard.contains_key(&a));
+    }
+
+    #[test]
+    fn test_concurrent_shards() {
+        const SHARDS: usize = 4;
+
+        let c = ShardedMemoCache::<u32, u32, SHARDS, 4>::new();
+        let barrier = Barrier::new(SHARDS);
+
+        // Every thread uses the keys of a separate shard, so the statistics of each shard are exactly its own.
+        thread::scope(|s| {
+            for shard in 0..SHARDS {
+                let (c, barrier) = (&c, &barrier);
+
+                s.spawn(move || {
+                    let keys = shard_keys::<SHARDS>(shard, 4);
+
+                    barrier.wait();
+
+                    for _ in 0..2 {
+                        keys.iter().for_each(|k| {
+                            assert_eq!(c.get_or_insert_with(k, |&k| k * 2), k * 2);
+                        });
+                    }
+                });
+            }
+        });
+
+        assert!(c.shards.iter().all(|s| s.stats()
+            == CacheStats {
+                hits: 4,
+                misses: 4,
+                len: 4
+            }));
+        assert_eq!(
+            c.stats(),
+            CacheStats {
+                hits: 16,
+                misses: 16,
+                len: 16
+            }
+        );
+    }
+}
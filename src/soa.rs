@@ -0,0 +1,273 @@
+//! Memo cache with structure-of-arrays storage, keeping keys apart from values.
+
+use crate::{
+    raw::{RawMemoCache, RawSplitMemoCache, SplitSlots},
+    EvictionPolicy, Fifo,
+};
+use core::{borrow::Borrow, marker::PhantomData};
+
+/// A small, fixed-size key/value cache with retention management, using structure-of-arrays storage.
+///
+/// Instead of an array of key/value slots, keys and values are stored in two separate arrays. Key scans only touch the
+/// key array, so (large) values are not pulled through the CPU cache on lookups. This is beneficial if values are
+/// large compared to keys.
+///
+/// The cache logic is shared with [`MemoCache`](crate::MemoCache), only the slot storage differs. As slots are filled
+/// front to back and are never emptied individually, slot occupancy is fully described by the number of used slots, so
+/// no occupancy bitmap (or other per-slot flags) is stored.
+///
+/// The API and eviction semantics are identical to [`MemoCache`](crate::MemoCache).
+pub struct SoaMemoCache<K, V, const SIZE: usize, P = Fifo>
+where
+    P: EvictionPolicy<K>,
+{
+    raw: RawSplitMemoCache<K, V, SIZE, P>,
+}
+
+impl<K, V, const SIZE: usize, P> SoaMemoCache<K, V, SIZE, P>
+where
+    K: Clone + Eq,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    /// Create a new cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SoaMemoCache;
+    ///
+    /// let c = SoaMemoCache::<u32, String, 4>::new();
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        Self::with_policy(P::INIT)
+    }
+
+    /// Create a new cache, using the given eviction policy state (see
+    /// [`MemoCache::with_policy`](crate::MemoCache::with_policy)).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{policy::Random, SoaMemoCache};
+    ///
+    /// let c = SoaMemoCache::<u32, String, 4, Random>::with_policy(Random::new(42));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn with_policy(policy: P) -> Self {
+        Self {
+            raw: RawMemoCache {
+                buffer: SplitSlots::EMPTY,
+                states: [const { P::SLOT_INIT }; SIZE],
+                policy,
+                len: 0,
+                marker: PhantomData,
+            },
+        }
+    }
+
+    /// Get the (fixed) capacity of the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SoaMemoCache;
+    ///
+    /// let c = SoaMemoCache::<u32, String, 8>::new();
+    ///
+    /// assert_eq!(c.capacity(), 8);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn capacity(&self) -> usize {
+        SIZE
+    }
+
+    /// Insert a key/value pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SoaMemoCache;
+    ///
+    /// let mut c = SoaMemoCache::<u32, &str, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn insert(&mut self, k: K, v: V) {
+        self.raw.insert(k, v);
+    }
+
+    /// Returns `true` if the cache contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SoaMemoCache;
+    ///
+    /// let mut c = SoaMemoCache::<u32, &str, 4>::new();
+    ///
+    /// assert_eq!(c.contains_key(&42), false);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.contains_key(&42), true);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.raw.contains_key(k)
+    }
+
+    /// Lookup a cache entry by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SoaMemoCache;
+    ///
+    /// let mut c = SoaMemoCache::<u32, &str, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.raw.get(k)
+    }
+
+    /// Lookup a cache entry by key (for mutation).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SoaMemoCache;
+    ///
+    /// let mut c = SoaMemoCache::<u32, &str, 4>::new();
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// if let Some(v) = c.get_mut(&42) {
+    ///     *v = "Another Answer";
+    /// }
+    ///
+    /// assert_eq!(c.get(&42), Some(&"Another Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.raw.get_mut(k)
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a reference to the found, or newly inserted value associated with the given key.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SoaMemoCache;
+    ///
+    /// let mut c = SoaMemoCache::<u32, &str, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// let v = c.get_or_insert_with(&42, |_| "The Answer");
+    ///
+    /// assert_eq!(v, &"The Answer");
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_insert_with<F>(&mut self, k: &K, f: F) -> &V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        self.raw.get_or_insert_with(k, f)
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a result with a reference to the found, or newly inserted value associated with the given key.
+    /// If `f` fails, the error is returned.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SoaMemoCache;
+    ///
+    /// let mut c = SoaMemoCache::<u32, &str, 4>::new();
+    ///
+    /// let v = c.get_or_try_insert_with(&42, |_| Ok::<_, &str>("The Answer"));
+    ///
+    /// assert_eq!(v, Ok(&"The Answer"));
+    ///
+    /// let v = c.get_or_try_insert_with(&17, |_| Err("Dunno"));
+    ///
+    /// assert_eq!(v, Err("Dunno"));
+    /// assert_eq!(c.get(&17), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_try_insert_with<F, E>(&mut self, k: &K, f: F) -> Result<&V, E>
+    where
+        F: FnOnce(&K) -> Result<V, E>,
+    {
+        self.raw.get_or_try_insert_with(k, f)
+    }
+
+    /// Clear the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SoaMemoCache;
+    ///
+    /// let mut c = SoaMemoCache::<u32, &str, 4>::new();
+    ///
+    /// c.insert(42, "The Answer");
+    /// c.clear();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn clear(&mut self) {
+        self.raw.clear();
+    }
+}
+
+impl<K, V, const SIZE: usize, P> Drop for SoaMemoCache<K, V, SIZE, P>
+where
+    P: EvictionPolicy<K>,
+{
+    fn drop(&mut self) {
+        self.raw.drop_used();
+    }
+}
+
+impl<K, V, const SIZE: usize, P> Default for SoaMemoCache<K, V, SIZE, P>
+where
+    K: Clone + Eq,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
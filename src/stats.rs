@@ -0,0 +1,57 @@
+//! Cache usage statistics.
+
+use core::{
+    iter::Sum,
+    ops::{Add, AddAssign},
+};
+
+/// Usage statistics of a cache.
+///
+/// Statistics of several caches (e.g. the shards of a `ShardedMemoCache`) can be merged by adding them up.
+///
+/// # Examples
+///
+/// ```
+/// use memo_cache::CacheStats;
+///
+/// let a = CacheStats { hits: 3, misses: 1, len: 1 };
+/// let b = CacheStats { hits: 1, misses: 1, len: 1 };
+///
+/// assert_eq!(a + b, CacheStats { hits: 4, misses: 2, len: 2 });
+/// assert_eq!([a, b].into_iter().sum::<CacheStats>(), a + b);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of lookups that found a value.
+    pub hits: usize,
+    /// Number of lookups that did not find a value.
+    pub misses: usize,
+    /// Number of key/value pairs in the cache.
+    pub len: usize,
+}
+
+impl Add for CacheStats {
+    type Output = Self;
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn add(self, other: Self) -> Self {
+        Self {
+            hits: self.hits + other.hits,
+            misses: self.misses + other.misses,
+            len: self.len + other.len,
+        }
+    }
+}
+
+impl AddAssign for CacheStats {
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for CacheStats {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
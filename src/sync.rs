@@ -0,0 +1,605 @@
+//! Thread-safe memo cache with interior locking.
+
+use crate::{CacheStats, EvictionPolicy, Fifo, MemoCache};
+use core::{
+    borrow::Borrow,
+    convert::Infallible,
+    future::{poll_fn, Future},
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use flight::{Flight, Outcome};
+use std::{
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    vec::Vec,
+};
+
+mod flight;
+
+/// A small, fixed-size, thread-safe key/value cache with retention management.
+///
+/// The cache is a [`MemoCache`] behind a mutex, so all methods take `&self`, and the cache can be shared between
+/// threads (e.g. in a `static` item, or an `Arc`). As references into the cache cannot outlive the lock, lookups
+/// return cloned values. For other access, the cache can be locked explicitly using [`lock`](Self::lock).
+///
+/// The lock is not held while computing values in [`get_or_insert_with`](Self::get_or_insert_with) and
+/// [`get_or_try_insert_with`](Self::get_or_try_insert_with), so (slow) computations do not block other threads.
+/// Concurrent misses on the same key are deduplicated: the first thread computes the value, while the other threads
+/// wait for it to land.
+///
+/// A panic while the lock is held does not poison the cache. Updates that panic halfway (e.g. in the `Hash`, `Eq` or
+/// `Drop` implementation of a key or value) empty the cache, leaking its contents, so it is always left consistent.
+///
+/// Lookup hits and misses are counted, see [`stats`](Self::stats).
+pub struct SyncMemoCache<K, V, const SIZE: usize, P = Fifo>
+where
+    P: EvictionPolicy<K>,
+{
+    state: Mutex<State<K, V, SIZE, P>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl<K, V, const SIZE: usize, P> SyncMemoCache<K, V, SIZE, P>
+where
+    K: Clone + Eq,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    /// Create a new cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SyncMemoCache;
+    ///
+    /// static CACHE: SyncMemoCache<u32, String, 4> = SyncMemoCache::new();
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        Self::with_policy(P::INIT)
+    }
+
+    /// Create a new cache, using the given eviction policy state (see
+    /// [`MemoCache::with_policy`](crate::MemoCache::with_policy)).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{policy::Random, SyncMemoCache};
+    ///
+    /// let c = SyncMemoCache::<u32, String, 4, Random>::with_policy(Random::new(42));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn with_policy(policy: P) -> Self {
+        Self {
+            state: Mutex::new(State {
+                cache: MemoCache::with_policy(policy),
+                flights: Vec::new(),
+            }),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get the (fixed) capacity of the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SyncMemoCache;
+    ///
+    /// let c = SyncMemoCache::<u32, String, 8>::new();
+    ///
+    /// assert_eq!(c.capacity(), 8);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn capacity(&self) -> usize {
+        SIZE
+    }
+
+    /// Lock the cache, for direct access.
+    ///
+    /// Other threads are blocked from accessing the cache until the returned guard is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SyncMemoCache;
+    ///
+    /// let c = SyncMemoCache::<u32, String, 4>::new();
+    ///
+    /// c.insert(42, "The Answer".to_owned());
+    ///
+    /// // Borrow the value, instead of cloning it.
+    /// assert_eq!(c.lock().get(&42).map(String::len), Some(10));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn lock(&self) -> SyncMemoCacheGuard<'_, K, V, SIZE, P> {
+        SyncMemoCacheGuard {
+            state: self.state.lock().unwrap_or_else(PoisonError::into_inner),
+        }
+    }
+
+    /// Count a lookup hit or miss.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn count(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Insert a key/value pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SyncMemoCache;
+    ///
+    /// let c = SyncMemoCache::<u32, &str, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some("The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn insert(&self, k: K, v: V) {
+        self.lock().insert(k, v);
+    }
+
+    /// Returns `true` if the cache contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SyncMemoCache;
+    ///
+    /// let c = SyncMemoCache::<u32, &str, 4>::new();
+    ///
+    /// assert_eq!(c.contains_key(&42), false);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.contains_key(&42), true);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.lock().contains_key(k)
+    }
+
+    /// Lookup a cache entry by key, returning a clone of the value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SyncMemoCache;
+    ///
+    /// let c = SyncMemoCache::<u32, &str, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some("The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let v = self.lock().get(k).cloned();
+        self.count(v.is_some());
+        v
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a clone of the found, or newly inserted value associated with the given key.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// The cache is not locked while `f` runs. If another thread is already computing the value for the same key, this
+    /// thread waits for that value instead of running `f`. If that computation fails (or panics), `f` is run after
+    /// all. If another thread inserted a value for the same key in the meantime, that value is kept and returned
+    /// instead.
+    ///
+    /// Calling this function for the same key from within `f` deadlocks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SyncMemoCache;
+    ///
+    /// let c = SyncMemoCache::<u32, &str, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// let v = c.get_or_insert_with(&42, |_| "The Answer");
+    ///
+    /// assert_eq!(v, "The Answer");
+    /// assert_eq!(c.get(&42), Some("The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_insert_with<F>(&self, k: &K, f: F) -> V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self.get_or_try_insert_with(k, |k| Ok::<_, Infallible>(f(k))) {
+            Ok(v) => v,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a result with a clone of the found, or newly inserted value associated with the given key.
+    /// If `f` fails, the error is returned.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// The cache is not locked while `f` runs. If another thread is already computing the value for the same key, this
+    /// thread waits for that value instead of running `f`. If that computation fails with an error of type `E`, the
+    /// error is returned to all waiting threads, and nothing is cached. If it fails otherwise (e.g. it panics), `f` is
+    /// run after all. If another thread inserted a value for the same key in the meantime, that value is kept and
+    /// returned instead.
+    ///
+    /// Calling this function for the same key from within `f` deadlocks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SyncMemoCache;
+    ///
+    /// let c = SyncMemoCache::<u32, &str, 4>::new();
+    ///
+    /// let v = c.get_or_try_insert_with(&42, |_| Ok::<_, &str>("The Answer"));
+    ///
+    /// assert_eq!(v, Ok("The Answer"));
+    ///
+    /// let v = c.get_or_try_insert_with(&17, |_| Err("Dunno"));
+    ///
+    /// assert_eq!(v, Err("Dunno"));
+    /// assert_eq!(c.get(&17), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_try_insert_with<F, E>(&self, k: &K, f: F) -> Result<V, E>
+    where
+        F: FnOnce(&K) -> Result<V, E>,
+        E: Clone + Send + Sync + 'static,
+    {
+        let mut count = true;
+
+        // Wait for in-flight computations of the value, until it is found or this thread has to compute it.
+        let flight = loop {
+            match self.lookup(k, core::mem::take(&mut count)) {
+                Lookup::Hit(v) => return Ok(v),
+                Lookup::Wait(flight) => {
+                    if let Some(result) = flight.wait().into_result() {
+                        return result;
+                    }
+                }
+                Lookup::Miss(flight) => break flight,
+            }
+        };
+
+        Landing::new(self, flight).complete(k, f(k))
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by the future returned
+    /// by `f`. Returns a clone of the found, or newly inserted value associated with the given key.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// This is the asynchronous version of [`get_or_insert_with`](Self::get_or_insert_with), which works with any
+    /// executor. The cache is not locked while the future runs. If another thread or task is already computing the
+    /// value for the same key, this task awaits that value instead of calling `f`. If the computing future is dropped
+    /// before completion, a waiting task takes over.
+    ///
+    /// Awaiting this function for the same key from within the future returned by `f` never completes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SyncMemoCache;
+    ///
+    /// let c = SyncMemoCache::<u32, String, 4>::new();
+    ///
+    /// let v = futures::executor::block_on(c.get_or_insert_with_async(&42, |&k| async move { k.to_string() }));
+    ///
+    /// assert_eq!(v, "42");
+    /// assert_eq!(c.get(&42), Some("42".to_owned()));
+    /// ```
+    pub async fn get_or_insert_with_async<F, Fut>(&self, k: &K, f: F) -> V
+    where
+        F: FnOnce(&K) -> Fut,
+        Fut: Future<Output = V>,
+    {
+        let f = |k: &K| {
+            let fut = f(k);
+            async { Ok::<_, Infallible>(fut.await) }
+        };
+
+        match self.get_or_try_insert_with_async(k, f).await {
+            Ok(v) => v,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by the future returned
+    /// by `f`. Returns a result with a clone of the found, or newly inserted value associated with the given key.
+    /// If the future fails, the error is returned.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// This is the asynchronous version of [`get_or_try_insert_with`](Self::get_or_try_insert_with), which works with
+    /// any executor. The cache is not locked while the future runs. If another thread or task is already computing the
+    /// value for the same key, this task awaits that value instead of calling `f`. If that computation fails with an
+    /// error of type `E`, the error is returned to all waiting threads and tasks, and nothing is cached. If it fails
+    /// otherwise (e.g. its future is dropped before completion), a waiting task takes over.
+    ///
+    /// Awaiting this function for the same key from within the future returned by `f` never completes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures::executor::block_on;
+    /// use memo_cache::SyncMemoCache;
+    ///
+    /// let c = SyncMemoCache::<u32, &str, 4>::new();
+    ///
+    /// let v = block_on(c.get_or_try_insert_with_async(&42, |_| async { Ok::<_, &str>("The Answer") }));
+    ///
+    /// assert_eq!(v, Ok("The Answer"));
+    ///
+    /// let v = block_on(c.get_or_try_insert_with_async(&17, |_| async { Err("Dunno") }));
+    ///
+    /// assert_eq!(v, Err("Dunno"));
+    /// assert_eq!(c.get(&17), None);
+    /// ```
+    pub async fn get_or_try_insert_with_async<F, Fut, E>(&self, k: &K, f: F) -> Result<V, E>
+    where
+        F: FnOnce(&K) -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+        E: Clone + Send + Sync + 'static,
+    {
+        let mut count = true;
+
+        // Await in-flight computations of the value, until it is found or this task has to compute it.
+        let flight = loop {
+            match self.lookup(k, core::mem::take(&mut count)) {
+                Lookup::Hit(v) => return Ok(v),
+                Lookup::Wait(flight) => {
+                    if let Some(result) = poll_fn(|cx| flight.poll_wait(cx)).await.into_result() {
+                        return result;
+                    }
+                }
+                Lookup::Miss(flight) => break flight,
+            }
+        };
+
+        // The landing is dropped if this future is, so waiting tasks can take over.
+        let landing = Landing::new(self, flight);
+        let result = f(k).await;
+
+        landing.complete(k, result)
+    }
+
+    /// Lookup a value, or the in-flight computation of it. If neither is found, a new flight is registered, which the
+    /// caller must complete. The lookup is counted if `count` is `true`.
+    fn lookup(&self, k: &K, count: bool) -> Lookup<V> {
+        let mut guard = self.lock();
+        let state = &mut *guard.state;
+
+        let lookup = if let Some(v) = state.cache.get(k) {
+            Lookup::Hit(v.clone())
+        } else if let Some((_, flight)) = state.flights.iter().find(|(key, _)| key == k) {
+            Lookup::Wait(Arc::clone(flight))
+        } else {
+            let flight = Arc::new(Flight::new());
+            state.flights.push((k.clone(), Arc::clone(&flight)));
+            Lookup::Miss(flight)
+        };
+
+        drop(guard);
+
+        if count {
+            self.count(matches!(lookup, Lookup::Hit(_)));
+        }
+
+        lookup
+    }
+
+    /// Get the usage statistics of the cache.
+    ///
+    /// Lookups through [`get`](Self::get), [`get_or_insert_with`](Self::get_or_insert_with) and
+    /// [`get_or_try_insert_with`](Self::get_or_try_insert_with) are counted, where lookups waiting for a value computed
+    /// by another thread count as misses. Clearing the cache does not reset the counts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{CacheStats, SyncMemoCache};
+    ///
+    /// let c = SyncMemoCache::<u32, &str, 4>::new();
+    ///
+    /// c.get_or_insert_with(&42, |_| "The Answer");
+    /// c.get_or_insert_with(&42, |_| "The Answer");
+    ///
+    /// assert_eq!(c.stats(), CacheStats { hits: 1, misses: 1, len: 1 });
+    /// ```
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            len: self.lock().raw.len,
+        }
+    }
+
+    /// Clear the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::SyncMemoCache;
+    ///
+    /// let c = SyncMemoCache::<u32, &str, 4>::new();
+    ///
+    /// c.insert(42, "The Answer");
+    /// c.clear();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+}
+
+impl<K, V, const SIZE: usize, P> Default for SyncMemoCache<K, V, SIZE, P>
+where
+    K: Clone + Eq,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cache state protected by the lock.
+struct State<K, V, const SIZE: usize, P>
+where
+    P: EvictionPolicy<K>,
+{
+    cache: MemoCache<K, V, SIZE, P>,
+    flights: Vec<(K, Arc<Flight<V>>)>,
+}
+
+/// Guard of a locked [`SyncMemoCache`], giving direct access to the cache.
+///
+/// The cache is unlocked when the guard is dropped.
+pub struct SyncMemoCacheGuard<'a, K, V, const SIZE: usize, P = Fifo>
+where
+    P: EvictionPolicy<K>,
+{
+    state: MutexGuard<'a, State<K, V, SIZE, P>>,
+}
+
+impl<K, V, const SIZE: usize, P> Deref for SyncMemoCacheGuard<'_, K, V, SIZE, P>
+where
+    P: EvictionPolicy<K>,
+{
+    type Target = MemoCache<K, V, SIZE, P>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.state.cache
+    }
+}
+
+impl<K, V, const SIZE: usize, P> DerefMut for SyncMemoCacheGuard<'_, K, V, SIZE, P>
+where
+    P: EvictionPolicy<K>,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.state.cache
+    }
+}
+
+/// Result of a value lookup.
+enum Lookup<V> {
+    /// The value was found.
+    Hit(V),
+    /// The value is being computed by another caller.
+    Wait(Arc<Flight<V>>),
+    /// The value was not found, and has to be computed by the caller.
+    Miss(Arc<Flight<V>>),
+}
+
+/// Completion of an in-flight computation, which removes the flight from the cache and publishes its outcome.
+///
+/// If the computation does not complete (i.e. it panics, or its future is dropped), the flight is abandoned on drop,
+/// so waiting callers retry.
+struct Landing<'a, K, V, const SIZE: usize, P>
+where
+    K: Clone + Eq,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    cache: &'a SyncMemoCache<K, V, SIZE, P>,
+    flight: Option<Arc<Flight<V>>>,
+}
+
+impl<'a, K, V, const SIZE: usize, P> Landing<'a, K, V, SIZE, P>
+where
+    K: Clone + Eq,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    /// Create a landing for the given flight.
+    fn new(cache: &'a SyncMemoCache<K, V, SIZE, P>, flight: Arc<Flight<V>>) -> Self {
+        Self {
+            cache,
+            flight: Some(flight),
+        }
+    }
+
+    /// Complete the computation of the value for key `k`: insert the computed value, or share the error.
+    fn complete<E>(mut self, k: &K, result: Result<V, E>) -> Result<V, E>
+    where
+        E: Clone + Send + Sync + 'static,
+    {
+        match result {
+            Ok(v) => Ok(self.land(
+                |c| c.get_or_insert_with(k, |_| v).clone(),
+                |v| Outcome::Value(v.clone()),
+            )),
+            Err(e) => {
+                self.land(|_| (), |_| Outcome::Error(Arc::new(e.clone())));
+                Err(e)
+            }
+        }
+    }
+
+    /// Remove the flight while running `f` under the lock (e.g. to insert the computed value), and publish the
+    /// outcome derived from the result of `f`.
+    ///
+    /// The flight is only taken once its outcome is known, so if `f` or `outcome` panics (e.g. cloning the value), the
+    /// flight is still abandoned on drop.
+    fn land<T, F, O>(&mut self, f: F, outcome: O) -> T
+    where
+        F: FnOnce(&mut MemoCache<K, V, SIZE, P>) -> T,
+        O: FnOnce(&T) -> Outcome<V>,
+    {
+        let result = {
+            let mut guard = self.cache.lock();
+            let state = &mut *guard.state;
+
+            if let Some(flight) = &self.flight {
+                state.flights.retain(|(_, f)| !Arc::ptr_eq(f, flight));
+            }
+
+            f(&mut state.cache)
+        };
+
+        let outcome = outcome(&result);
+
+        if let Some(flight) = self.flight.take() {
+            flight.land(outcome);
+        }
+
+        result
+    }
+}
+
+impl<K, V, const SIZE: usize, P> Drop for Landing<'_, K, V, SIZE, P>
+where
+    K: Clone + Eq,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    fn drop(&mut self) {
+        if self.flight.is_some() {
+            self.land(|_| (), |_| Outcome::Abandoned);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! In-flight value computations, shared between the threads (or tasks) that miss on the same key.
+
+use core::task::{Context, Poll, Waker};
+use std::{
+    any::Any,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    vec::Vec,
+};
+
+/// Outcome of a value computation.
+pub(crate) enum Outcome<V> {
+    /// The computed value.
+    Value(V),
+    /// The (type-erased) error of a failed computation.
+    Error(Arc<dyn Any + Send + Sync>),
+    /// The computation was abandoned (i.e. it panicked, or its future was dropped).
+    Abandoned,
+}
+
+impl<V> Outcome<V> {
+    /// Get the result for a waiting caller, or `None` if the caller has to retry (i.e. the computation was abandoned,
+    /// or it failed with an error of another type than `E`).
+    pub(crate) fn into_result<E>(self) -> Option<Result<V, E>>
+    where
+        E: Clone + 'static,
+    {
+        match self {
+            Self::Value(v) => Some(Ok(v)),
+            Self::Error(e) => e.downcast_ref::<E>().cloned().map(Err),
+            Self::Abandoned => None,
+        }
+    }
+}
+
+impl<V: Clone> Clone for Outcome<V> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Value(v) => Self::Value(v.clone()),
+            Self::Error(e) => Self::Error(Arc::clone(e)),
+            Self::Abandoned => Self::Abandoned,
+        }
+    }
+}
+
+/// Flight state, protected by the flight lock.
+struct State<V> {
+    outcome: Option<Outcome<V>>,
+    wakers: Vec<Waker>,
+}
+
+/// A value computation in progress, which other threads can wait for, and other tasks can await.
+pub(crate) struct Flight<V> {
+    state: Mutex<State<V>>,
+    landed: Condvar,
+}
+
+impl<V: Clone> Flight<V> {
+    /// Create a new flight.
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                outcome: None,
+                wakers: Vec::new(),
+            }),
+            landed: Condvar::new(),
+        }
+    }
+
+    /// Lock the flight state.
+    fn lock(&self) -> MutexGuard<'_, State<V>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Publish the outcome of the computation, waking all waiting threads and tasks.
+    pub(crate) fn land(&self, outcome: Outcome<V>) {
+        let wakers = {
+            let mut state = self.lock();
+            state.outcome = Some(outcome);
+            core::mem::take(&mut state.wakers)
+        };
+
+        self.landed.notify_all();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Block until the outcome of the computation is published, and get it.
+    pub(crate) fn wait(&self) -> Outcome<V> {
+        let state = self
+            .landed
+            .wait_while(self.lock(), |s| s.outcome.is_none())
+            .unwrap_or_else(PoisonError::into_inner);
+
+        // The wait condition guarantees the outcome is set.
+        state.outcome.clone().unwrap_or(Outcome::Abandoned)
+    }
+
+    /// Get the outcome of the computation if it is published, or register the task to be woken when it is.
+    pub(crate) fn poll_wait(&self, cx: &mut Context<'_>) -> Poll<Outcome<V>> {
+        let mut state = self.lock();
+
+        if let Some(outcome) = &state.outcome {
+            return Poll::Ready(outcome.clone());
+        }
+
+        if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+
+        Poll::Pending
+    }
+}
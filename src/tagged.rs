@@ -0,0 +1,349 @@
+//! Memo cache with a fingerprint tag array to speed up key scans.
+
+mod group;
+
+use crate::{hash, EvictionPolicy, Fifo, MemoCache};
+use core::{borrow::Borrow, hash::Hash};
+
+/// Get the one-byte fingerprint tag of a key.
+#[cfg_attr(feature = "inline-more", inline)]
+fn tag<Q: Hash + ?Sized>(k: &Q) -> u8 {
+    // The top bits are used, these are best mixed by the hash finalizer.
+    (hash::hash(k) >> 56) as u8
+}
+
+/// A small, fixed-size key/value cache with retention management, using fingerprint tags.
+///
+/// Next to the key/value slots, a one-byte hash tag is stored per slot. Lookups compare the tag of the requested key
+/// against all tags first (32 tags at a time, using portable SWAR bit tricks on `u64` words), and only run the
+/// (potentially expensive) key comparison on tag matches. This speeds up lookups for keys that are costly to compare
+/// (e.g. strings or larger structures), and for larger capacities. For small capacities with cheap keys, the plain
+/// [`MemoCache`] is usually faster, as it does not have to hash keys.
+///
+/// The API and eviction semantics are identical to [`MemoCache`], except that keys must implement [`Hash`].
+pub struct TaggedMemoCache<K, V, const SIZE: usize, P = Fifo>
+where
+    P: EvictionPolicy<K>,
+{
+    inner: MemoCache<K, V, SIZE, P>,
+    tags: [u8; SIZE],
+}
+
+impl<K, V, const SIZE: usize, P> TaggedMemoCache<K, V, SIZE, P>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    /// Create a new cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::TaggedMemoCache;
+    ///
+    /// let c = TaggedMemoCache::<u32, String, 4>::new();
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn new() -> Self {
+        Self::with_policy(P::INIT)
+    }
+
+    /// Create a new cache, using the given eviction policy state (see
+    /// [`MemoCache::with_policy`](crate::MemoCache::with_policy)).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::{policy::Random, TaggedMemoCache};
+    ///
+    /// let c = TaggedMemoCache::<u32, String, 4, Random>::with_policy(Random::new(42));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn with_policy(policy: P) -> Self {
+        Self {
+            inner: MemoCache::with_policy(policy),
+            tags: [0; SIZE],
+        }
+    }
+
+    /// Get the (fixed) capacity of the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::TaggedMemoCache;
+    ///
+    /// let c = TaggedMemoCache::<u32, String, 8>::new();
+    ///
+    /// assert_eq!(c.capacity(), 8);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub const fn capacity(&self) -> usize {
+        SIZE
+    }
+
+    /// Insert a key/value pair.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::TaggedMemoCache;
+    ///
+    /// let mut c = TaggedMemoCache::<u32, &str, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn insert(&mut self, k: K, v: V) {
+        let t = tag(&k);
+
+        match self.get_key_index(&k, t) {
+            Some(i) => *self.inner.raw.hit_mut(i) = v,
+            None => {
+                self.replace_and_shift(k, v, t);
+            }
+        }
+    }
+
+    /// Returns `true` if the cache contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::TaggedMemoCache;
+    ///
+    /// let mut c = TaggedMemoCache::<u32, &str, 4>::new();
+    ///
+    /// assert_eq!(c.contains_key(&42), false);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.contains_key(&42), true);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.get_key_index(k, tag(k)).is_some()
+    }
+
+    /// Lookup a cache entry by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::TaggedMemoCache;
+    ///
+    /// let mut c = TaggedMemoCache::<u32, &str, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.get_key_index(k, tag(k)).map(|i| self.inner.raw.hit(i))
+    }
+
+    /// Lookup a cache entry by key (for mutation).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::TaggedMemoCache;
+    ///
+    /// let mut c = TaggedMemoCache::<u32, &str, 4>::new();
+    ///
+    /// c.insert(42, "The Answer");
+    ///
+    /// if let Some(v) = c.get_mut(&42) {
+    ///     *v = "Another Answer";
+    /// }
+    ///
+    /// assert_eq!(c.get(&42), Some(&"Another Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.get_key_index(k, tag(k))
+            .map(|i| self.inner.raw.hit_mut(i))
+    }
+
+    /// Get the index for a given key with the given tag, if found.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn get_key_index<Q>(&self, k: &Q, t: u8) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.inner
+            .raw
+            .get_key_index_among(k, group::match_tag(&self.tags[..self.inner.raw.len], t))
+    }
+
+    /// Insert a new key/value pair with the given tag, replacing a slot as selected by the eviction policy.
+    /// Returns the index of the replaced slot.
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn replace_and_shift(&mut self, k: K, v: V, t: u8) -> usize {
+        let i = self.inner.raw.replace_and_shift(k, v);
+        self.tags[i] = t;
+        i
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a reference to the found, or newly inserted value associated with the given key.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::TaggedMemoCache;
+    ///
+    /// let mut c = TaggedMemoCache::<u32, &str, 4>::new();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    ///
+    /// let v = c.get_or_insert_with(&42, |_| "The Answer");
+    ///
+    /// assert_eq!(v, &"The Answer");
+    /// assert_eq!(c.get(&42), Some(&"The Answer"));
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_insert_with<F>(&mut self, k: &K, f: F) -> &V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        let t = tag(k);
+
+        let i = match self.get_key_index(k, t) {
+            Some(i) => {
+                self.inner.raw.touch(i);
+                i
+            }
+            None => self.replace_and_shift(k.clone(), f(k), t),
+        };
+
+        // SAFETY: The key index was retrieved from a found key, or the slot was just filled.
+        unsafe { self.inner.raw.value_unchecked(i) }
+    }
+
+    /// Get a value, or, if it does not exist in the cache, insert it using the value computed by `f`.
+    /// Returns a result with a reference to the found, or newly inserted value associated with the given key.
+    /// If `f` fails, the error is returned.
+    /// If a value is inserted, the key is cloned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::TaggedMemoCache;
+    ///
+    /// let mut c = TaggedMemoCache::<u32, &str, 4>::new();
+    ///
+    /// let v = c.get_or_try_insert_with(&42, |_| Ok::<_, &str>("The Answer"));
+    ///
+    /// assert_eq!(v, Ok(&"The Answer"));
+    ///
+    /// let v = c.get_or_try_insert_with(&17, |_| Err("Dunno"));
+    ///
+    /// assert_eq!(v, Err("Dunno"));
+    /// assert_eq!(c.get(&17), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn get_or_try_insert_with<F, E>(&mut self, k: &K, f: F) -> Result<&V, E>
+    where
+        F: FnOnce(&K) -> Result<V, E>,
+    {
+        let t = tag(k);
+
+        let i = match self.get_key_index(k, t) {
+            Some(i) => {
+                self.inner.raw.touch(i);
+                i
+            }
+            None => self.replace_and_shift(k.clone(), f(k)?, t),
+        };
+
+        // SAFETY: The key index was retrieved from a found key, or the slot was just filled.
+        Ok(unsafe { self.inner.raw.value_unchecked(i) })
+    }
+
+    /// Clear the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memo_cache::TaggedMemoCache;
+    ///
+    /// let mut c = TaggedMemoCache::<u32, &str, 4>::new();
+    ///
+    /// c.insert(42, "The Answer");
+    /// c.clear();
+    ///
+    /// assert_eq!(c.get(&42), None);
+    /// ```
+    #[cfg_attr(feature = "inline-more", inline)]
+    pub fn clear(&mut self) {
+        // Tags of unused slots are never read, so they do not have to be reset.
+        self.inner.clear();
+    }
+}
+
+impl<K, V, const SIZE: usize, P> Default for TaggedMemoCache<K, V, SIZE, P>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+    P: EvictionPolicy<K>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests_internal {
+    use super::*;
+
+    #[test]
+    fn test_tags_follow_slots() {
+        let mut c = TaggedMemoCache::<u32, u32, 2>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+        c.insert(3, 30); // Replaces slot 0.
+
+        assert_eq!(c.tags[0], tag(&3));
+        assert_eq!(c.tags[1], tag(&2));
+    }
+
+    #[test]
+    fn test_tag_collision() {
+        // Find two keys with colliding tags, these must still be told apart by the key comparison.
+        let a = 0u32;
+        let b = (1..).find(|k| tag(k) == tag(&a)).unwrap();
+
+        let mut c = TaggedMemoCache::<u32, u32, 4>::new();
+
+        c.insert(a, 1);
+        c.insert(b, 2);
+
+        assert_eq!(c.tags[0], c.tags[1]);
+        assert_eq!(c.get(&a), Some(&1));
+        assert_eq!(c.get(&b), Some(&2));
+    }
+}
@@ -0,0 +1,172 @@
+//! Portable SWAR (SIMD within a register) matching of one-byte tags, a group of tags at a time.
+
+/// Word type holding a number of tags (one per byte lane).
+type Word = u64;
+
+/// Number of tags per word.
+const WORD_WIDTH: usize = core::mem::size_of::<Word>();
+
+/// Number of words per group.
+const GROUP_WORDS: usize = 4;
+
+/// Number of tags matched at once.
+pub(crate) const GROUP_WIDTH: usize = WORD_WIDTH * GROUP_WORDS;
+
+/// Lowest bit of every byte lane set.
+const LSBS: Word = Word::from_ne_bytes([0x01; WORD_WIDTH]);
+
+/// Highest bit of every byte lane set.
+const MSBS: Word = Word::from_ne_bytes([0x80; WORD_WIDTH]);
+
+/// Load a partial word of tags, tag `i` in byte lane `i`. Missing lanes are zero.
+#[cfg_attr(feature = "inline-more", inline)]
+fn load_partial(tags: &[u8]) -> Word {
+    let mut bytes = [0; WORD_WIDTH];
+    bytes[..tags.len()].copy_from_slice(tags);
+    Word::from_le_bytes(bytes)
+}
+
+/// Match `t` against the byte lanes of a word, returning a compact bit mask with bit `i` set if lane `i` matches.
+///
+/// Matches are exact, except that lanes following a matching lane may be reported as false positives if their tag is
+/// `t ^ 1` (as in hashbrown's generic group implementation). Callers always compare keys on a match, so this is
+/// harmless.
+#[cfg_attr(feature = "inline-more", inline)]
+fn match_word(word: Word, t: u8) -> u32 {
+    // Matching lanes become zero, and only zero lanes get their high bit set by the subtraction without having it set
+    // beforehand (except for lanes of value one receiving a borrow, which produce a false positive).
+    let x = word ^ (LSBS * t as Word);
+    let m = x.wrapping_sub(LSBS) & !x & MSBS;
+
+    // Gather the lane high bits into the top byte, lane `i` at bit `56 + i`.
+    ((m >> 7).wrapping_mul(0x0102_0408_1020_4080) >> 56) as u32
+}
+
+/// Match `t` against a group of (at most `GROUP_WIDTH`) tags, returning a bit mask with bit `i` set if tag `i`
+/// (possibly) matches.
+#[cfg_attr(feature = "inline-more", inline)]
+fn match_group(tags: &[u8], t: u8) -> u32 {
+    debug_assert!(tags.len() <= GROUP_WIDTH);
+
+    if let Ok(group) = <&[u8; GROUP_WIDTH]>::try_from(tags) {
+        // Fast path for full groups, loading whole words.
+        return (0..GROUP_WORDS).fold(0, |mask, w| {
+            let word =
+                Word::from_le_bytes(group[w * WORD_WIDTH..][..WORD_WIDTH].try_into().unwrap());
+            mask | match_word(word, t) << (w * WORD_WIDTH)
+        });
+    }
+
+    let mask = tags
+        .chunks(WORD_WIDTH)
+        .enumerate()
+        .fold(0, |mask, (w, chunk)| {
+            mask | match_word(load_partial(chunk), t) << (w * WORD_WIDTH)
+        });
+
+    // Missing lanes of a partial group must not be reported.
+    mask & ((1 << tags.len()) - 1)
+}
+
+/// Iterator over the indices of all tags (possibly) matching a given tag, matching a group of tags at a time.
+pub(crate) struct TagMatches<'a> {
+    /// Tags of the groups still to be matched.
+    tags: &'a [u8],
+    /// Tag to match.
+    tag: u8,
+    /// Index of the first tag of the current group.
+    base: usize,
+    /// Index of the first tag of the next group.
+    next: usize,
+    /// Remaining matches in the current group.
+    mask: u32,
+}
+
+impl Iterator for TagMatches<'_> {
+    type Item = usize;
+
+    #[cfg_attr(feature = "inline-more", inline)]
+    fn next(&mut self) -> Option<usize> {
+        while self.mask == 0 {
+            if self.tags.is_empty() {
+                return None;
+            }
+
+            let (group, rest) = self.tags.split_at(GROUP_WIDTH.min(self.tags.len()));
+
+            self.base = self.next;
+            self.next += group.len();
+            self.mask = match_group(group, self.tag);
+            self.tags = rest;
+        }
+
+        let i = self.mask.trailing_zeros() as usize;
+        self.mask &= self.mask - 1;
+        Some(self.base + i)
+    }
+}
+
+/// Get the indices of all tags (possibly) matching `t`, in ascending order.
+///
+/// The tags are matched a group at a time, so every index of a matching tag is reported, but a few non-matching
+/// indices may be reported as well.
+#[cfg_attr(feature = "inline-more", inline)]
+pub(crate) fn match_tag(tags: &[u8], t: u8) -> TagMatches<'_> {
+    TagMatches {
+        tags,
+        tag: t,
+        base: 0,
+        next: 0,
+        mask: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests_internal {
+    use super::*;
+
+    #[test]
+    fn test_match_word() {
+        let word = Word::from_le_bytes([7, 0, 7, 3, 3, 3, 255, 7]);
+
+        assert_eq!(match_word(word, 7), 0b1000_0101);
+        assert_eq!(match_word(word, 255), 0b0100_0000);
+        assert_eq!(match_word(word, 42), 0);
+    }
+
+    #[test]
+    fn test_match_tag() {
+        let mut x = 42u32;
+        let mut tags = [0u8; 40];
+
+        // Fewer rounds under Miri, which is much slower.
+        for round in 0..if cfg!(miri) { 2 } else { 100 } {
+            // Use a small tag range to get plenty of matches (and adjacent ones).
+            tags.iter_mut().for_each(|t| {
+                x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                *t = ((x >> 16) % 4) as u8 * if round % 2 == 0 { 1 } else { 85 };
+            });
+
+            for len in 0..=tags.len() {
+                for t in [0, 1, 2, 3, 85, 170, 255] {
+                    let tags = &tags[..len];
+                    let mut found = [0; 40];
+                    let n = match_tag(tags, t)
+                        .zip(found.iter_mut())
+                        .map(|(i, f)| *f = i)
+                        .count();
+                    let found = &found[..n];
+
+                    // All exact matches are found, in order, and false positives are limited to `t ^ 1` tags.
+                    assert!(found.windows(2).all(|w| w[0] < w[1]));
+                    assert!(found
+                        .iter()
+                        .all(|&i| i < len && (tags[i] == t || tags[i] == t ^ 1)));
+                    assert!((0..len)
+                        .filter(|&i| tags[i] == t)
+                        .all(|i| found.contains(&i)));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+mod tests_external {
+    use memo_cache::{AtomicMemoCache, MemoCache, Word};
+    use std::thread;
+
+    #[test]
+    fn test_empty() {
+        let c = AtomicMemoCache::<bool, bool, 2>::new();
+
+        assert_eq!(c.capacity(), 2);
+        assert_eq!(c.get(&true), None);
+        assert_eq!(c.get(&false), None);
+    }
+
+    #[test]
+    fn test_insert_existing() {
+        let c = AtomicMemoCache::<char, i8, 2>::new();
+
+        c.insert('a', -1);
+        c.insert('b', -2);
+        c.insert('a', -3);
+
+        // Updating a value does not claim a slot.
+        assert_eq!(c.get(&'a'), Some(-3));
+        assert_eq!(c.get(&'b'), Some(-2));
+    }
+
+    #[test]
+    fn test_get_or_try_insert_with() {
+        let c = AtomicMemoCache::<u32, f32, 2>::new();
+
+        assert_eq!(
+            c.get_or_try_insert_with(&1, |_| Err::<f32, _>("fail")),
+            Err("fail")
+        );
+        assert_eq!(
+            c.get_or_try_insert_with(&1, |_| Ok::<_, &str>(1.5)),
+            Ok(1.5)
+        );
+        assert_eq!(c.get_or_try_insert_with(&1, |_| Err("fail")), Ok(1.5));
+    }
+
+    #[test]
+    fn test_clear() {
+        let c = AtomicMemoCache::<u64, f64, 4>::new();
+
+        (0..4).for_each(|k| c.insert(k, k as f64));
+        c.clear();
+
+        assert!((0..4).all(|k| !c.contains_key(&k)));
+
+        c.insert(4, 4.0);
+
+        assert_eq!(c.get(&4), Some(4.0));
+    }
+
+    #[test]
+    fn test_words() {
+        fn roundtrip<T: Word + PartialEq + std::fmt::Debug>(v: T) {
+            assert_eq!(T::from_word(v.into_word()), v);
+        }
+
+        roundtrip(u8::MAX);
+        roundtrip(i16::MIN);
+        roundtrip(-1i32);
+        roundtrip(u64::MAX);
+        roundtrip(isize::MIN);
+        roundtrip(-0.5f32);
+        roundtrip(f64::MAX);
+        roundtrip(true);
+        roundtrip('é');
+    }
+
+    #[test]
+    fn test_same_as_memo_cache() {
+        let c = AtomicMemoCache::<u32, u32, 4>::new();
+        let mut m = MemoCache::<u32, u32, 4>::new();
+
+        for k in [1, 2, 3, 1, 4, 5, 2, 6, 1, 7, 3, 3, 8] {
+            assert_eq!(
+                c.get_or_insert_with(&k, |&k| k * 2),
+                *m.get_or_insert_with(&k, |&k| k * 2)
+            );
+        }
+
+        for k in 0..10 {
+            assert_eq!(c.get(&k), m.get(&k).copied());
+        }
+    }
+
+    #[test]
+    fn test_threads() {
+        static CACHE: AtomicMemoCache<u64, u64, 8> = AtomicMemoCache::new();
+
+        // Keys and values are written in separate words, so a torn read would show a mismatching value.
+        thread::scope(|s| {
+            for t in 0..4 {
+                s.spawn(move || {
+                    for i in 0..if cfg!(miri) { 100 } else { 10_000 } {
+                        let k = (i * 7 + t) % 16;
+                        assert_eq!(CACHE.get_or_insert_with(&k, |&k| !k), !k);
+                    }
+                });
+            }
+        });
+    }
+}
@@ -0,0 +1,115 @@
+//! Tests shared by all cache types with the `MemoCache` API.
+
+/// Instantiate the shared tests for a cache type.
+///
+/// Expects a function `with_cache::<K, V, P, R>(f)` calling `f` with an empty cache of 16 slots using eviction policy
+/// `P` (a function instead of a constructor, so the cache can borrow its slots), and the policy used to compare the
+/// retention of the cache with a `MemoCache` of the same capacity.
+macro_rules! cache_tests {
+    ($with_cache:item, same_as_memo_cache: $policy:ty) => {
+        $with_cache
+
+        #[test]
+        fn test_empty() {
+            with_cache::<bool, bool, ::memo_cache::Fifo, _>(|c| {
+                assert_eq!(c.capacity(), 16);
+                assert_eq!(c.get(&true), None);
+                assert_eq!(c.get(&false), None);
+            });
+        }
+
+        #[test]
+        fn test_borrowed_key() {
+            with_cache::<String, i32, ::memo_cache::Fifo, _>(|mut c| {
+                c.insert("hello".to_owned(), 42);
+
+                // Lookups by a borrowed form of the key must find the same slot.
+                assert!(c.contains_key("hello"));
+                assert_eq!(c.get("hello"), Some(&42));
+                assert_eq!(c.get("world"), None);
+
+                if let Some(v) = c.get_mut("hello") {
+                    *v = 17;
+                }
+
+                assert_eq!(c.get("hello"), Some(&17));
+            });
+        }
+
+        #[test]
+        fn test_get_or_try_insert_with() {
+            with_cache::<u32, u32, ::memo_cache::Fifo, _>(|mut c| {
+                assert_eq!(
+                    c.get_or_try_insert_with(&1, |_| Err::<u32, _>("error")),
+                    Err("error")
+                );
+                assert!(!c.contains_key(&1));
+
+                assert_eq!(c.get_or_try_insert_with(&1, |_| Ok::<_, ()>(10)), Ok(&10));
+                assert_eq!(c.get_or_try_insert_with(&1, |_| Err(())), Ok(&10));
+            });
+        }
+
+        #[test]
+        fn test_same_as_memo_cache() {
+            let mut a = ::memo_cache::MemoCache::<u32, u32, 16, $policy>::new();
+
+            with_cache::<u32, u32, $policy, _>(|mut b| {
+                let mut x = 42u32;
+
+                // Retention is identical to the plain cache of the same capacity.
+                // Fewer iterations under Miri, which is much slower.
+                for _ in 0..if cfg!(miri) { 100 } else { 5_000 } {
+                    x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    let k = (x >> 16) % 64;
+
+                    assert_eq!(
+                        a.get_or_insert_with(&k, |&k| k * 2),
+                        b.get_or_insert_with(&k, |&k| k * 2)
+                    );
+                    assert!((0..64).all(|k| a.contains_key(&k) == b.contains_key(&k)));
+                }
+            });
+        }
+
+        #[test]
+        fn test_drop() {
+            use ::std::rc::Rc;
+
+            let key = Rc::new(());
+            let value = Rc::new(());
+
+            with_cache::<(u32, Rc<()>), Rc<()>, ::memo_cache::Fifo, _>(|mut c| {
+                // Partially filled, the empty slots must not be dropped.
+                c.insert((0, key.clone()), value.clone());
+                c.insert((1, key.clone()), value.clone());
+
+                assert_eq!(Rc::strong_count(&key), 3);
+                assert_eq!(Rc::strong_count(&value), 3);
+
+                // Replaced keys and values are dropped.
+                (2..40).for_each(|i| c.insert((i, key.clone()), value.clone()));
+
+                assert_eq!(Rc::strong_count(&key), 17);
+                assert_eq!(Rc::strong_count(&value), 17);
+
+                // Updated values are dropped.
+                c.insert((39, key.clone()), value.clone());
+
+                assert_eq!(Rc::strong_count(&key), 17);
+                assert_eq!(Rc::strong_count(&value), 17);
+
+                c.clear();
+
+                assert_eq!(Rc::strong_count(&key), 1);
+                assert_eq!(Rc::strong_count(&value), 1);
+
+                c.insert((0, key.clone()), value.clone());
+                drop(c);
+
+                assert_eq!(Rc::strong_count(&key), 1);
+                assert_eq!(Rc::strong_count(&value), 1);
+            });
+        }
+    };
+}
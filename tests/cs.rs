@@ -0,0 +1,102 @@
+mod tests_external {
+    use memo_cache::{policy::Lru, CsMemoCache, MemoCache};
+    use std::thread;
+
+    #[test]
+    fn test_empty() {
+        let c = CsMemoCache::<u32, f32, 2>::new();
+
+        assert_eq!(c.capacity(), 2);
+        assert_eq!(c.get(&42), None);
+        assert!(!c.contains_key(&42));
+    }
+
+    #[test]
+    fn test_borrowed_key() {
+        let c = CsMemoCache::<String, usize, 2>::new();
+
+        c.insert("hello".to_owned(), 5);
+
+        assert_eq!(c.get("hello"), Some(5));
+        assert!(c.contains_key("hello"));
+        assert!(!c.contains_key("world"));
+    }
+
+    #[test]
+    fn test_get_or_try_insert_with() {
+        let c = CsMemoCache::<u32, f32, 2>::new();
+
+        assert_eq!(
+            c.get_or_try_insert_with(&1, |_| Err::<f32, _>("fail")),
+            Err("fail")
+        );
+        assert!(!c.contains_key(&1));
+        assert_eq!(
+            c.get_or_try_insert_with(&1, |_| Ok::<_, &str>(1.5)),
+            Ok(1.5)
+        );
+        assert_eq!(c.get_or_try_insert_with(&1, |_| Err("fail")), Ok(1.5));
+    }
+
+    #[test]
+    fn test_insert_during_calculation() {
+        let c = CsMemoCache::<u32, u32, 2>::new();
+
+        // Simulate an interrupt handler inserting the same key while the value is calculated.
+        let v = c.get_or_insert_with(&1, |&k| {
+            c.insert(k, 17);
+            42
+        });
+
+        assert_eq!(v, 17);
+        assert_eq!(c.get(&1), Some(17));
+    }
+
+    #[test]
+    fn test_recursive() {
+        static CACHE: CsMemoCache<u64, u64, 128> = CsMemoCache::new();
+
+        fn fib(n: u64) -> u64 {
+            CACHE.get_or_insert_with(&n, |&n| if n < 2 { n } else { fib(n - 1) + fib(n - 2) })
+        }
+
+        assert_eq!(fib(90), 2_880_067_194_370_816_120);
+    }
+
+    #[test]
+    fn test_same_as_memo_cache() {
+        let c = CsMemoCache::<u32, u32, 4, Lru>::new();
+        let mut m = MemoCache::<u32, u32, 4, Lru>::new();
+
+        for k in [1, 2, 3, 1, 4, 5, 2, 6, 1, 7, 3, 3, 8] {
+            assert_eq!(
+                c.get_or_insert_with(&k, |&k| k * 2),
+                *m.get_or_insert_with(&k, |&k| k * 2)
+            );
+        }
+
+        for k in 0..10 {
+            assert_eq!(c.get(&k), m.get(&k).copied());
+        }
+
+        c.clear();
+
+        assert!((0..10).all(|k| !c.contains_key(&k)));
+    }
+
+    #[test]
+    fn test_threads() {
+        static CACHE: CsMemoCache<u32, u32, 8> = CsMemoCache::new();
+
+        thread::scope(|s| {
+            for t in 0..4 {
+                s.spawn(move || {
+                    for i in 0..if cfg!(miri) { 100 } else { 1_000 } {
+                        let k = (i * 7 + t) % 16;
+                        assert_eq!(CACHE.get_or_insert_with(&k, |&k| !k), !k);
+                    }
+                });
+            }
+        });
+    }
+}
@@ -0,0 +1,19 @@
+#[macro_use]
+mod common;
+
+mod tests_external {
+    use memo_cache::{policy::Lru, DynMemoCache, EvictionPolicy};
+
+    cache_tests! {
+        fn with_cache<K: Clone + Eq, V: Clone, P: EvictionPolicy<K>, R>(f: impl FnOnce(DynMemoCache<K, V, P>) -> R) -> R {
+            f(DynMemoCache::new(16))
+        },
+        same_as_memo_cache: Lru
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_capacity() {
+        DynMemoCache::<u32, u32>::new(0);
+    }
+}
@@ -0,0 +1,202 @@
+mod tests_external {
+    use memo_cache::{policy::Lru, thread_local_memo, MemoCache};
+    use std::{panic, thread};
+
+    #[test]
+    fn test_empty() {
+        thread_local_memo! {
+            static CACHE: ThreadLocalMemo<u32, f32, 2>;
+        }
+
+        assert_eq!(CACHE.capacity(), 2);
+        assert_eq!(CACHE.get(&42), None);
+        assert!(!CACHE.contains_key(&42));
+    }
+
+    #[test]
+    fn test_borrowed_key() {
+        thread_local_memo! {
+            static CACHE: ThreadLocalMemo<String, usize, 2>;
+        }
+
+        CACHE.insert("hello".to_owned(), 5);
+
+        assert_eq!(CACHE.get("hello"), Some(5));
+        assert!(CACHE.contains_key("hello"));
+        assert!(!CACHE.contains_key("world"));
+    }
+
+    #[test]
+    fn test_get_or_try_insert_with() {
+        thread_local_memo! {
+            static CACHE: ThreadLocalMemo<u32, f32, 2>;
+        }
+
+        assert_eq!(
+            CACHE.get_or_try_insert_with(&1, |_| Err::<f32, _>("fail")),
+            Err("fail")
+        );
+        assert!(!CACHE.contains_key(&1));
+        assert_eq!(
+            CACHE.get_or_try_insert_with(&1, |_| Ok::<_, &str>(1.5)),
+            Ok(1.5)
+        );
+        assert_eq!(CACHE.get_or_try_insert_with(&1, |_| Err("fail")), Ok(1.5));
+    }
+
+    #[test]
+    fn test_recursive() {
+        thread_local_memo! {
+            static CACHE: ThreadLocalMemo<u64, u64, 128>;
+        }
+
+        fn fib(n: u64) -> u64 {
+            CACHE.get_or_insert_with(&n, |&n| if n < 2 { n } else { fib(n - 1) + fib(n - 2) })
+        }
+
+        assert_eq!(fib(90), 2_880_067_194_370_816_120);
+    }
+
+    #[test]
+    fn test_with_cache_reentrant() {
+        thread_local_memo! {
+            static CACHE: ThreadLocalMemo<u32, u32, 2>;
+        }
+
+        let result = panic::catch_unwind(|| CACHE.with_cache(|_| CACHE.get(&1)));
+
+        assert!(result.is_err());
+
+        // The cache is usable after the panic.
+        CACHE.insert(1, 2);
+
+        assert_eq!(CACHE.get(&1), Some(2));
+    }
+
+    #[test]
+    fn test_same_as_memo_cache() {
+        thread_local_memo! {
+            static CACHE: ThreadLocalMemo<u32, u32, 4, Lru>;
+        }
+
+        let mut m = MemoCache::<u32, u32, 4, Lru>::new();
+
+        for k in [1, 2, 3, 1, 4, 5, 2, 6, 1, 7, 3, 3, 8] {
+            assert_eq!(
+                CACHE.get_or_insert_with(&k, |&k| k * 2),
+                *m.get_or_insert_with(&k, |&k| k * 2)
+            );
+        }
+
+        for k in 0..10 {
+            assert_eq!(CACHE.with_cache(|c| c.get(&k).copied()), m.get(&k).copied());
+        }
+    }
+
+    #[test]
+    fn test_threads() {
+        thread_local_memo! {
+            static CACHE: ThreadLocalMemo<u32, usize, 4>;
+        }
+
+        CACHE.insert(1, 0);
+
+        thread::scope(|s| {
+            for t in 1..=3 {
+                s.spawn(move || {
+                    // Values of other threads are not visible.
+                    assert_eq!(CACHE.get_or_insert_with(&1, |_| t), t);
+                    assert_eq!(CACHE.get(&1), Some(t));
+                });
+            }
+        });
+
+        assert_eq!(CACHE.get(&1), Some(0));
+    }
+
+    #[test]
+    fn test_stats() {
+        thread_local_memo! {
+            static CACHE: ThreadLocalMemo<u32, u32, 4>;
+        }
+
+        CACHE.get_or_insert_with(&1, |&k| k);
+        CACHE.get_or_insert_with(&1, |&k| k);
+        CACHE.insert(2, 2);
+
+        // Joining (instead of a thread scope) waits for the thread-local caches to be dropped.
+        let handles: Vec<_> = (0..3)
+            .map(|_| {
+                thread::spawn(|| {
+                    (0..8).for_each(|k| {
+                        CACHE.get_or_insert_with(&k, |&k| k);
+                    });
+
+                    assert!(CACHE.stats().len >= 4);
+                })
+            })
+            .collect();
+
+        handles.into_iter().for_each(|h| h.join().unwrap());
+
+        // The caches of exited threads are not counted.
+        let stats = CACHE.stats();
+
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1 + 3 * 8);
+        assert_eq!(stats.len, 2);
+
+        CACHE.clear();
+
+        // Clearing the cache does not reset the counts.
+        assert_eq!(CACHE.stats().len, 0);
+        assert_eq!(CACHE.stats().misses, 1 + 3 * 8);
+    }
+
+    #[test]
+    fn test_stats_panic() {
+        thread_local_memo! {
+            static CACHE: ThreadLocalMemo<u32, u32, 4>;
+        }
+
+        thread::spawn(|| {
+            CACHE.insert(1, 1);
+
+            // Entries inserted before a panic are counted.
+            let result = panic::catch_unwind(|| {
+                CACHE.with_cache(|c| {
+                    c.insert(2, 2);
+                    panic!("interrupted");
+                })
+            });
+
+            assert!(result.is_err());
+            assert_eq!(CACHE.stats().len, 2);
+
+            // Entries removed by a panic are no longer counted.
+            let result = panic::catch_unwind(|| {
+                CACHE.with_cache(|c| {
+                    c.clear();
+                    panic!("interrupted");
+                })
+            });
+
+            assert!(result.is_err());
+            assert_eq!(CACHE.stats().len, 0);
+
+            CACHE.insert(3, 3);
+
+            let result = panic::catch_unwind(|| {
+                CACHE.get_or_insert_with(&4, |_| panic!("calculation failed"))
+            });
+
+            assert!(result.is_err());
+            assert_eq!(CACHE.stats().len, 1);
+        })
+        .join()
+        .unwrap();
+
+        // The cache of the exited thread is no longer counted.
+        assert_eq!(CACHE.stats().len, 0);
+    }
+}
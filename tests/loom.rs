@@ -0,0 +1,126 @@
+//! Model checks of the lock-free `AtomicMemoCache`, run using:
+//!
+//! ```sh
+//! RUSTFLAGS="--cfg loom" cargo test --release --test loom
+//! ```
+//!
+//! Loom explores the interleavings of operations that follow the spawning of a thread, so the reading side of each
+//! model runs in the spawned thread.
+
+#![cfg(loom)]
+
+mod tests_external {
+    use loom::{sync::Arc, thread};
+    use memo_cache::AtomicMemoCache;
+
+    /// Value stored for a key, so torn key/value pairs can be detected.
+    fn value(k: u64) -> u64 {
+        k * 10 + 1
+    }
+
+    #[test]
+    fn test_read_during_write() {
+        loom::model(|| {
+            let c = Arc::new(AtomicMemoCache::<u64, u64, 1>::new());
+
+            c.insert(1, value(1));
+
+            // The reader sees the old pair, the new pair, or a miss, but never a mix of both.
+            let reader = {
+                let c = Arc::clone(&c);
+                thread::spawn(move || [1, 2].map(|k| c.get(&k).map(|v| (k, v))))
+            };
+
+            c.insert(2, value(2));
+
+            for (k, v) in reader.join().unwrap().into_iter().flatten() {
+                assert_eq!(v, value(k));
+            }
+
+            assert_eq!(c.get(&1), None);
+            assert_eq!(c.get(&2), Some(value(2)));
+        });
+    }
+
+    #[test]
+    fn test_update_during_read() {
+        loom::model(|| {
+            let c = Arc::new(AtomicMemoCache::<u64, u64, 1>::new());
+
+            c.insert(1, 1);
+
+            let reader = {
+                let c = Arc::clone(&c);
+                thread::spawn(move || c.get(&1))
+            };
+
+            c.insert(1, 2);
+
+            // A lookup that overlaps the update misses.
+            assert!(matches!(reader.join().unwrap(), None | Some(1 | 2)));
+            assert_eq!(c.get(&1), Some(2));
+        });
+    }
+
+    #[test]
+    fn test_clear_during_read() {
+        loom::model(|| {
+            let c = Arc::new(AtomicMemoCache::<u64, u64, 1>::new());
+
+            c.insert(1, value(1));
+
+            let reader = {
+                let c = Arc::clone(&c);
+                thread::spawn(move || c.get(&1))
+            };
+
+            c.clear();
+
+            assert!(matches!(reader.join().unwrap(), None | Some(11)));
+            assert_eq!(c.get(&1), None);
+        });
+    }
+
+    #[test]
+    fn test_concurrent_writes() {
+        loom::model(|| {
+            let c = Arc::new(AtomicMemoCache::<u64, u64, 2>::new());
+
+            let writer = {
+                let c = Arc::clone(&c);
+                thread::spawn(move || c.get_or_insert_with(&1, |&k| value(k)))
+            };
+
+            assert_eq!(c.get_or_insert_with(&2, |&k| value(k)), value(2));
+            assert_eq!(writer.join().unwrap(), value(1));
+
+            // The writers claimed different slots through the cursor, so both pairs are retained.
+            assert_eq!(c.get(&1), Some(value(1)));
+            assert_eq!(c.get(&2), Some(value(2)));
+        });
+    }
+
+    #[test]
+    fn test_concurrent_writes_same_slot() {
+        loom::model(|| {
+            let c = Arc::new(AtomicMemoCache::<u64, u64, 1>::new());
+
+            let writer = {
+                let c = Arc::clone(&c);
+                thread::spawn(move || c.insert(1, value(1)))
+            };
+
+            c.insert(2, value(2));
+            writer.join().unwrap();
+
+            // Exactly one of the writes is retained, intact.
+            let found: Vec<_> = [1, 2]
+                .into_iter()
+                .filter_map(|k| c.get(&k).map(|v| (k, v)))
+                .collect();
+
+            assert_eq!(found.len(), 1);
+            assert!(found.iter().all(|&(k, v)| v == value(k)));
+        });
+    }
+}
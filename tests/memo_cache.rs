@@ -1,5 +1,13 @@
+// The original tests spell out their assertions in full.
+#![allow(
+    clippy::assertions_on_constants,
+    clippy::bool_assert_comparison,
+    clippy::get_first,
+    clippy::useless_vec
+)]
+
 mod tests_external {
-    use memo_cache::MemoCache;
+    use memo_cache::{EvictionPolicy, MemoCache, SlotKeys};
 
     #[test]
     fn test_empty() {
@@ -259,4 +267,270 @@ mod tests_external {
         assert_eq!(c.get(&kv0.0), Some(&42)); // Updated.
         assert_eq!(c.get(&kv1.0), Some(&kv1.1));
     }
+
+    #[test]
+    fn test_custom_policy() {
+        /// Always evicts the first slot.
+        struct EvictFirst;
+
+        impl<K> EvictionPolicy<K> for EvictFirst {
+            type SlotState = ();
+
+            const INIT: Self = EvictFirst;
+            const SLOT_INIT: Self::SlotState = ();
+
+            fn on_hit(&self, _: &[()], _: usize, _: &K) {}
+            fn on_insert(&mut self, _: &mut [()], _: usize, _: &K) {}
+
+            fn victim<S: SlotKeys<K> + ?Sized>(&mut self, _: &mut [()], _: &K, _: &S) -> usize {
+                0
+            }
+        }
+
+        let mut c = MemoCache::<i32, i32, 3, EvictFirst>::new();
+
+        c.insert(1, 1);
+        c.insert(2, 2);
+        c.insert(3, 3);
+        c.insert(4, 4);
+
+        assert_eq!(c.get(&1), None);
+        assert_eq!(c.get(&2), Some(&2));
+        assert_eq!(c.get(&3), Some(&3));
+        assert_eq!(c.get(&4), Some(&4));
+
+        c.insert(5, 5);
+
+        assert_eq!(c.get(&4), None);
+        assert_eq!(c.get(&2), Some(&2));
+        assert_eq!(c.get(&3), Some(&3));
+        assert_eq!(c.get(&5), Some(&5));
+    }
+
+    #[test]
+    fn test_custom_policy_clear() {
+        /// Always evicts the slot given on creation.
+        struct EvictSlot(usize);
+
+        impl<K> EvictionPolicy<K> for EvictSlot {
+            type SlotState = ();
+
+            const INIT: Self = EvictSlot(0);
+            const SLOT_INIT: Self::SlotState = ();
+
+            fn on_hit(&self, _: &[()], _: usize, _: &K) {}
+            fn on_insert(&mut self, _: &mut [()], _: usize, _: &K) {}
+
+            fn victim<S: SlotKeys<K> + ?Sized>(&mut self, _: &mut [()], _: &K, _: &S) -> usize {
+                self.0
+            }
+        }
+
+        let mut c = MemoCache::<i32, i32, 3, EvictSlot>::with_policy(EvictSlot(2));
+
+        for k in 1..=4 {
+            c.insert(k, k);
+        }
+
+        assert_eq!(c.get(&3), None);
+
+        // Clearing resets the policy to its initial state, dropping the parameter it was created with.
+        c.clear();
+
+        for k in 1..=4 {
+            c.insert(k, k);
+        }
+
+        assert_eq!(c.get(&1), None);
+        assert_eq!(c.get(&3), Some(&3));
+    }
+
+    #[test]
+    fn test_size_of() {
+        use std::mem::size_of;
+
+        // Slots carry no occupancy flag, so a slot is exactly the size of a key/value pair. Next to the slots, only the
+        // number of used slots and the FIFO cursor are stored.
+        assert_eq!(
+            size_of::<MemoCache<u32, f32, 64>>(),
+            64 * 8 + 2 * size_of::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_drop() {
+        use std::rc::Rc;
+
+        let key = Rc::new(());
+        let value = Rc::new(());
+
+        let mut c = MemoCache::<_, _, 4>::new();
+
+        // Partially filled, the empty slots must not be dropped.
+        c.insert((0, key.clone()), value.clone());
+        c.insert((1, key.clone()), value.clone());
+
+        assert_eq!(Rc::strong_count(&key), 3);
+        assert_eq!(Rc::strong_count(&value), 3);
+
+        // Replaced keys and values are dropped.
+        (2..10).for_each(|i| c.insert((i, key.clone()), value.clone()));
+
+        assert_eq!(Rc::strong_count(&key), 5);
+        assert_eq!(Rc::strong_count(&value), 5);
+
+        // Updated values are dropped.
+        c.insert((9, key.clone()), value.clone());
+
+        assert_eq!(Rc::strong_count(&key), 5);
+        assert_eq!(Rc::strong_count(&value), 5);
+
+        c.clear();
+
+        assert_eq!(Rc::strong_count(&key), 1);
+        assert_eq!(Rc::strong_count(&value), 1);
+
+        c.insert((0, key.clone()), value.clone());
+        drop(c);
+
+        assert_eq!(Rc::strong_count(&key), 1);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn test_zero_sized() {
+        let mut c = MemoCache::<(), (), 2>::new();
+
+        assert_eq!(c.get(&()), None);
+        assert_eq!(c.get_or_insert_with(&(), |_| ()), &());
+        assert_eq!(c.get(&()), Some(&()));
+    }
+
+    #[test]
+    fn test_panicking_calculation() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut c = MemoCache::<u32, String, 2>::new();
+
+        c.insert(1, "one".to_owned());
+
+        // A panic while calculating a value must leave the cache untouched.
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            c.get_or_insert_with(&2, |_| panic!("calculation failed"));
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(c.get(&1).map(String::as_str), Some("one"));
+        assert_eq!(c.get(&2), None);
+    }
+
+    #[test]
+    fn test_panicking_key() {
+        use memo_cache::policy::{Arc, S3Fifo, TinyLfu};
+        use std::{
+            hash::{Hash, Hasher},
+            panic::{catch_unwind, AssertUnwindSafe},
+        };
+
+        /// Key of which the hash panics if so requested.
+        #[derive(Clone, PartialEq, Eq)]
+        struct Key(u32, bool);
+
+        impl Hash for Key {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                assert!(!self.1, "hash failed");
+                self.0.hash(state);
+            }
+        }
+
+        fn check<P: EvictionPolicy<Key>>() {
+            let mut c = MemoCache::<Key, u32, 4, P>::new();
+
+            for k in 0..8 {
+                c.insert(Key(k, false), k);
+                assert_eq!(c.get(&Key(k, false)), Some(&k));
+            }
+
+            // The policy hashes the key while it is inserted, after it may have unlinked the victim already.
+            let result = catch_unwind(AssertUnwindSafe(|| c.insert(Key(100, true), 100)));
+
+            assert!(result.is_err());
+
+            // The cache was emptied, and is fully usable again.
+            assert!((0..8).all(|k| !c.contains_key(&Key(k, false))));
+
+            for k in 0..100 {
+                c.insert(Key(k % 10, false), k);
+                assert_eq!(c.get(&Key(k % 10, false)), Some(&k));
+                assert!((0..10).filter(|&k| c.contains_key(&Key(k, false))).count() <= 4);
+            }
+        }
+
+        check::<S3Fifo>();
+        check::<Arc>();
+        check::<TinyLfu>();
+    }
+
+    #[test]
+    fn test_panicking_drop() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        /// Value of which the drop panics if so requested.
+        #[derive(Clone)]
+        struct Value(u32, bool);
+
+        impl Drop for Value {
+            fn drop(&mut self) {
+                assert!(!self.1 || std::thread::panicking(), "drop failed");
+            }
+        }
+
+        let mut c = MemoCache::<u32, Value, 2, memo_cache::policy::Lru>::new();
+
+        c.insert(1, Value(1, true));
+        c.insert(2, Value(2, false));
+
+        // Replacing the value panics while dropping the evicted one.
+        let result = catch_unwind(AssertUnwindSafe(|| c.insert(3, Value(3, false))));
+
+        assert!(result.is_err());
+
+        // The cache was emptied (leaking the remaining values), and is fully usable again.
+        assert!((1..=3).all(|k| !c.contains_key(&k)));
+
+        for k in 0..10 {
+            c.insert(k, Value(k, false));
+            assert_eq!(c.get(&k).map(|v| v.0), Some(k));
+        }
+    }
+
+    #[test]
+    fn test_static() {
+        use memo_cache::{policy::Lru, SoaMemoCache};
+        use std::sync::Mutex;
+
+        static CACHE: Mutex<MemoCache<u32, u32, 4, Lru>> = Mutex::new(MemoCache::new());
+        static SOA_CACHE: Mutex<SoaMemoCache<u32, u32, 4>> = Mutex::new(SoaMemoCache::new());
+
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                std::thread::spawn(move || {
+                    for k in 0..100 {
+                        let k = (k * t) % 8;
+
+                        assert_eq!(
+                            *CACHE.lock().unwrap().get_or_insert_with(&k, |&k| k + 1),
+                            k + 1
+                        );
+                        assert_eq!(
+                            *SOA_CACHE.lock().unwrap().get_or_insert_with(&k, |&k| k + 1),
+                            k + 1
+                        );
+                    }
+                })
+            })
+            .collect();
+
+        handles.into_iter().for_each(|h| h.join().unwrap());
+    }
 }
@@ -0,0 +1,684 @@
+mod tests_external {
+    use memo_cache::{
+        policy::{Arc, Clock, Fifo, Lfu, Lru, Random, S3Fifo, Sieve, Slru, TinyLfu, TwoChoices},
+        EvictionPolicy, MemoCache, SlotKeys,
+    };
+
+    /// Run a skewed pseudo-random workload, checking basic cache invariants along the way.
+    fn stress<P: EvictionPolicy<u32>>() {
+        let mut c = MemoCache::<u32, u32, 16, P>::new();
+        let mut x = 42u32;
+
+        for round in 0..2 {
+            // Fewer iterations under Miri, which is much slower.
+            for _ in 0..if cfg!(miri) { 20 } else { 5_000 } {
+                x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                let k = (x >> 16) % 64 / (1 + (x >> 8) % 4);
+
+                assert_eq!(c.get_or_insert_with(&k, |&k| k * 2), &(k * 2));
+                assert_eq!(c.get(&k), Some(&(k * 2)));
+                assert!((0..64).filter(|k| c.contains_key(k)).count() <= 16);
+            }
+
+            if round == 0 {
+                c.clear();
+                assert!((0..64).all(|k| !c.contains_key(&k)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_stress() {
+        stress::<Fifo>();
+        stress::<Lru>();
+        stress::<Clock>();
+        stress::<Sieve>();
+        stress::<S3Fifo>();
+        stress::<Lfu>();
+        stress::<TinyLfu>();
+        stress::<TinyLfu<Lru>>();
+        stress::<TinyLfu<Sieve>>();
+        stress::<TinyLfu<S3Fifo>>();
+        stress::<TinyLfu<Arc>>();
+        stress::<TinyLfu<Slru>>();
+        stress::<Arc>();
+        stress::<Random>();
+        stress::<TwoChoices>();
+        stress::<Slru>();
+        stress::<Slru<0>>();
+        stress::<Slru<100>>();
+    }
+
+    #[test]
+    fn test_wrapped_policy() {
+        /// Counts evictions, delegating the decisions to another policy.
+        struct CountEvictions<P> {
+            inner: P,
+            evictions: usize,
+        }
+
+        impl<K, P: EvictionPolicy<K>> EvictionPolicy<K> for CountEvictions<P> {
+            type SlotState = P::SlotState;
+
+            const INIT: Self = Self {
+                inner: P::INIT,
+                evictions: 0,
+            };
+            const SLOT_INIT: Self::SlotState = P::SLOT_INIT;
+
+            fn on_hit(&self, states: &[Self::SlotState], index: usize, key: &K) {
+                self.inner.on_hit(states, index, key);
+            }
+
+            fn on_insert(&mut self, states: &mut [Self::SlotState], index: usize, key: &K) {
+                self.inner.on_insert(states, index, key);
+            }
+
+            fn victim<S: SlotKeys<K> + ?Sized>(
+                &mut self,
+                states: &mut [Self::SlotState],
+                key: &K,
+                keys: &S,
+            ) -> usize {
+                self.evictions += 1;
+                self.inner.victim(states, key, keys)
+            }
+        }
+
+        let mut c = MemoCache::<i32, i32, 2, CountEvictions<Random>>::with_policy(CountEvictions {
+            inner: Random::new(7),
+            evictions: 0,
+        });
+        let mut l = MemoCache::<i32, i32, 2, CountEvictions<Lru>>::with_policy(CountEvictions {
+            inner: Lru::new(),
+            evictions: 0,
+        });
+
+        for k in 0..5 {
+            c.insert(k, k);
+            l.insert(k, k);
+        }
+
+        // Evictions are decided by the inner policy.
+        assert_eq!((0..5).filter(|k| c.contains_key(k)).count(), 2);
+        assert!((0..5).all(|k| l.contains_key(&k) == (k >= 3)));
+    }
+
+    #[test]
+    fn test_lru_get_protects() {
+        let mut c = MemoCache::<i32, i32, 3, Lru>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+        c.insert(3, 30);
+
+        // Use the oldest key, making key 2 the least recently used one.
+        assert_eq!(c.get(&1), Some(&10));
+
+        c.insert(4, 40);
+
+        assert_eq!(c.get(&2), None);
+        assert_eq!(c.get(&1), Some(&10));
+        assert_eq!(c.get(&3), Some(&30));
+        assert_eq!(c.get(&4), Some(&40));
+    }
+
+    #[test]
+    fn test_lru_all_lookups_mark_used() {
+        let mut c = MemoCache::<i32, i32, 3, Lru>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+        c.insert(3, 30);
+
+        // Mark keys 1, 2 and 3 as used (in that order), using each of the lookup variants.
+        assert!(c.get_mut(&1).is_some());
+        assert_eq!(c.get_or_insert_with(&2, |_| unreachable!()), &20);
+        assert_eq!(c.get_or_try_insert_with(&3, |_| Err(())), Ok(&30));
+
+        c.insert(4, 40);
+
+        assert!(!c.contains_key(&1));
+        assert!(c.contains_key(&2));
+
+        c.insert(5, 50);
+
+        assert!(!c.contains_key(&2));
+        assert!(c.contains_key(&3));
+        assert!(c.contains_key(&4));
+        assert!(c.contains_key(&5));
+    }
+
+    #[test]
+    fn test_lru_clear() {
+        let mut c = MemoCache::<i32, i32, 2, Lru>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+        c.clear();
+
+        assert_eq!(c.get(&1), None);
+
+        c.insert(3, 30);
+        c.insert(4, 40);
+        c.insert(5, 50);
+
+        assert_eq!(c.get(&3), None);
+        assert_eq!(c.get(&4), Some(&40));
+        assert_eq!(c.get(&5), Some(&50));
+    }
+
+    #[test]
+    fn test_clock_second_chance() {
+        let mut c = MemoCache::<i32, i32, 3, Clock>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+        c.insert(3, 30);
+
+        // Set the reference bit of the oldest key, the hand will skip it once.
+        assert_eq!(c.get(&1), Some(&10));
+
+        c.insert(4, 40);
+
+        assert!(c.contains_key(&1));
+        assert!(!c.contains_key(&2));
+
+        c.insert(5, 50);
+
+        assert!(!c.contains_key(&3));
+
+        // The reference bit of key 1 was cleared by the passing hand, so it is next.
+        c.insert(6, 60);
+
+        assert!(!c.contains_key(&1));
+        assert!(c.contains_key(&4));
+        assert!(c.contains_key(&5));
+        assert!(c.contains_key(&6));
+    }
+
+    #[test]
+    fn test_clock_all_referenced() {
+        let mut c = MemoCache::<i32, i32, 2, Clock>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+
+        assert_eq!(c.get(&1), Some(&10));
+        assert_eq!(c.get(&2), Some(&20));
+
+        // With all reference bits set, the hand makes a full turn and evicts the oldest slot.
+        c.insert(3, 30);
+
+        assert!(!c.contains_key(&1));
+        assert!(c.contains_key(&2));
+        assert!(c.contains_key(&3));
+    }
+
+    #[test]
+    fn test_sieve_retains_visited() {
+        let mut c = MemoCache::<i32, i32, 3, Sieve>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+        c.insert(3, 30);
+
+        // Mark the oldest key as visited, the hand skips it and clears the bit.
+        assert_eq!(c.get(&1), Some(&10));
+
+        c.insert(4, 40);
+
+        assert!(c.contains_key(&1));
+        assert!(!c.contains_key(&2));
+
+        // The hand continues from where it stopped, evicting newer unvisited keys while the retained key stays put.
+        c.insert(5, 50);
+
+        assert!(!c.contains_key(&3));
+
+        c.insert(6, 60);
+
+        assert!(c.contains_key(&1));
+        assert!(!c.contains_key(&4));
+        assert!(c.contains_key(&5));
+        assert!(c.contains_key(&6));
+    }
+
+    #[test]
+    fn test_sieve_clear() {
+        let mut c = MemoCache::<i32, i32, 2, Sieve>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+        c.clear();
+
+        c.insert(3, 30);
+        c.insert(4, 40);
+        c.insert(5, 50);
+
+        assert!(!c.contains_key(&3));
+        assert!(c.contains_key(&4));
+        assert!(c.contains_key(&5));
+    }
+
+    #[test]
+    fn test_s3fifo_scan_resistance() {
+        let mut c = MemoCache::<i32, i32, 10, S3Fifo>::new();
+
+        for k in 0..5 {
+            c.insert(k, k);
+            assert_eq!(c.get(&k), Some(&k));
+        }
+
+        // A scan of one-hit wonders only churns the small queue.
+        for k in 100..200 {
+            c.insert(k, k);
+        }
+
+        for k in 0..5 {
+            assert_eq!(c.get(&k), Some(&k));
+        }
+
+        assert!(c.contains_key(&199));
+        assert!(!c.contains_key(&100));
+    }
+
+    #[test]
+    fn test_s3fifo_ghost_hit() {
+        let mut c = MemoCache::<i32, i32, 4, S3Fifo<25, 4>>::new();
+
+        for k in 1..=5 {
+            c.insert(k, k);
+        }
+
+        // Key 1 was evicted from the small queue, and is remembered in the ghost set.
+        assert!(!c.contains_key(&1));
+
+        // Reinserting it puts it in the main queue, out of reach of the scan.
+        c.insert(1, 1);
+
+        for k in 6..=10 {
+            c.insert(k, k);
+        }
+
+        assert!(c.contains_key(&1));
+        assert!(c.contains_key(&10));
+    }
+
+    #[test]
+    fn test_s3fifo_clear() {
+        let mut c = MemoCache::<i32, i32, 2, S3Fifo>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+        c.clear();
+
+        c.insert(3, 30);
+        c.insert(4, 40);
+        c.insert(5, 50);
+
+        assert!(!c.contains_key(&3));
+        assert!(c.contains_key(&4));
+        assert!(c.contains_key(&5));
+    }
+
+    #[test]
+    fn test_lfu_evicts_least_used() {
+        let mut c = MemoCache::<i32, i32, 3, Lfu>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+        c.insert(3, 30);
+
+        for _ in 0..3 {
+            assert_eq!(c.get(&1), Some(&10));
+        }
+
+        assert_eq!(c.get_or_insert_with(&2, |_| unreachable!()), &20);
+
+        c.insert(4, 40);
+
+        assert!(!c.contains_key(&3));
+
+        c.insert(5, 50);
+
+        assert!(!c.contains_key(&4));
+        assert!(c.contains_key(&1));
+        assert!(c.contains_key(&2));
+        assert!(c.contains_key(&5));
+    }
+
+    #[test]
+    fn test_lfu_aging() {
+        let mut c = MemoCache::<i32, i32, 2, Lfu<6>>::new();
+
+        c.insert(1, 10);
+
+        for _ in 0..3 {
+            assert_eq!(c.get(&1), Some(&10));
+        }
+
+        c.insert(2, 20);
+
+        // The sixth access (the first hit on key 2) halves all counters, after which key 2 catches up with key 1.
+        for _ in 0..2 {
+            assert_eq!(c.get(&2), Some(&20));
+        }
+
+        c.insert(3, 30);
+
+        assert!(!c.contains_key(&1));
+        assert!(c.contains_key(&2));
+        assert!(c.contains_key(&3));
+    }
+
+    #[test]
+    fn test_tinylfu_rejects_cold_keys() {
+        let mut c = MemoCache::<i32, i32, 4, TinyLfu>::new();
+
+        for k in 1..=3 {
+            c.insert(k, k);
+
+            for _ in 0..3 {
+                assert_eq!(c.get(&k), Some(&k));
+            }
+        }
+
+        // Cold keys are not admitted, and only churn the bypass slot.
+        for k in 100..200 {
+            assert_eq!(c.get_or_insert_with(&k, |&k| k), &k);
+        }
+
+        for k in 1..=3 {
+            assert_eq!(c.get(&k), Some(&k));
+        }
+
+        assert!(c.contains_key(&199));
+        assert!(!c.contains_key(&198));
+    }
+
+    #[test]
+    fn test_tinylfu_admits_frequent_keys() {
+        let mut c = MemoCache::<i32, i32, 4, TinyLfu>::new();
+
+        for k in 1..=3 {
+            c.insert(k, k);
+        }
+
+        // Key 42 is requested often, but keeps getting pushed out of the bypass slot.
+        for k in 100..110 {
+            assert_eq!(c.get_or_insert_with(&42, |_| 42), &42);
+            c.insert(k, k);
+        }
+
+        // Eventually it is seen more often than the FIFO victim, and admitted.
+        assert_eq!(c.get(&42), Some(&42));
+        assert!(!c.contains_key(&1));
+    }
+
+    #[test]
+    fn test_tinylfu_wraps_lru() {
+        let mut c = MemoCache::<i32, i32, 4, TinyLfu<Lru>>::new();
+
+        for k in 1..=3 {
+            c.insert(k, k);
+
+            for _ in 0..3 {
+                assert_eq!(c.get(&k), Some(&k));
+            }
+        }
+
+        // Cold keys are not admitted over the LRU victims.
+        for k in 100..200 {
+            assert_eq!(c.get_or_insert_with(&k, |&k| k), &k);
+        }
+
+        assert!((1..=3).all(|k| c.contains_key(&k)));
+
+        // A frequently requested key is eventually admitted, replacing one of the hot keys.
+        for k in 200..210 {
+            assert_eq!(c.get_or_insert_with(&42, |_| 42), &42);
+            c.insert(k, k);
+        }
+
+        assert_eq!(c.get(&42), Some(&42));
+        assert_eq!((1..=3).filter(|k| c.contains_key(k)).count(), 2);
+    }
+
+    #[test]
+    fn test_tinylfu_single_slot() {
+        let mut c = MemoCache::<i32, i32, 1, TinyLfu>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+
+        assert!(!c.contains_key(&1));
+        assert_eq!(c.get(&2), Some(&20));
+    }
+
+    #[test]
+    fn test_tinylfu_rejection_keeps_lru_victim() {
+        let mut c = MemoCache::<i32, i32, 4, TinyLfu<Lru>>::new();
+
+        for k in 1..=3 {
+            c.insert(k, k);
+        }
+
+        // Key 5 takes the bypass slot, and is seen often.
+        c.insert(5, 5);
+        assert_eq!(c.get(&5), Some(&5));
+        assert_eq!(c.get(&5), Some(&5));
+
+        // Key 4 is rejected in favor of the LRU victim, key 1, and pushes key 5 out of the bypass slot.
+        c.insert(4, 4);
+
+        assert!((1..=4).all(|k| c.contains_key(&k)));
+        assert!(!c.contains_key(&5));
+
+        // Key 1 is still the least recently used key, so it makes room for key 5.
+        c.insert(5, 5);
+
+        assert!(!c.contains_key(&1));
+        assert!((2..=3).all(|k| c.contains_key(&k)));
+        assert!(c.contains_key(&5));
+    }
+
+    #[test]
+    fn test_tinylfu_rejection_keeps_s3fifo_victim() {
+        let mut c = MemoCache::<i32, i32, 4, TinyLfu<S3Fifo>>::new();
+
+        for k in 1..=3 {
+            c.insert(k, k);
+        }
+
+        c.insert(5, 5);
+        assert_eq!(c.get(&5), Some(&5));
+        assert_eq!(c.get(&5), Some(&5));
+
+        // Key 4 is rejected in favor of the oldest key of the small queue, key 1, which stays there.
+        c.insert(4, 4);
+
+        assert!((1..=4).all(|k| c.contains_key(&k)));
+        assert!(!c.contains_key(&5));
+
+        // Key 1 is evicted for key 5, and remembered in the ghost set.
+        c.insert(5, 5);
+
+        assert!(!c.contains_key(&1));
+        assert!((2..=3).all(|k| c.contains_key(&k)));
+        assert!(c.contains_key(&5));
+    }
+
+    #[test]
+    fn test_tinylfu_rejection_keeps_arc_victim() {
+        let mut c = MemoCache::<i32, i32, 4, TinyLfu<Arc>>::new();
+
+        for k in 1..=3 {
+            c.insert(k, k);
+        }
+
+        // Key 3 moves to T2, keys 1 and 2 stay in T1.
+        assert_eq!(c.get(&3), Some(&3));
+
+        c.insert(5, 5);
+        assert_eq!(c.get(&5), Some(&5));
+        assert_eq!(c.get(&5), Some(&5));
+
+        // Key 4 is rejected in favor of the oldest key of T1, key 1, which stays resident.
+        c.insert(4, 4);
+
+        assert!((1..=4).all(|k| c.contains_key(&k)));
+        assert!(!c.contains_key(&5));
+
+        // Key 1 is still the oldest key of T1, so it makes room for key 5.
+        c.insert(5, 5);
+
+        assert!(!c.contains_key(&1));
+        assert!((2..=3).all(|k| c.contains_key(&k)));
+        assert!(c.contains_key(&5));
+    }
+
+    #[test]
+    fn test_arc_scan_resistance() {
+        let mut c = MemoCache::<i32, i32, 4, Arc>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+
+        // Hit keys move to the frequency list (T2).
+        assert_eq!(c.get(&1), Some(&10));
+        assert_eq!(c.get(&2), Some(&20));
+
+        // Keys seen only once are evicted from the recency list (T1) first.
+        for k in 100..200 {
+            c.insert(k, k);
+        }
+
+        assert!(c.contains_key(&1));
+        assert!(c.contains_key(&2));
+        assert!(c.contains_key(&199));
+    }
+
+    #[test]
+    fn test_arc_adapts_to_recency() {
+        let mut c = MemoCache::<i32, i32, 2, Arc>::new();
+
+        c.insert(1, 10);
+        assert_eq!(c.get(&1), Some(&10));
+
+        // Key 2 is evicted from T1 in favor of key 3, and remembered in ghost list B1.
+        c.insert(2, 20);
+        c.insert(3, 30);
+
+        assert!(!c.contains_key(&2));
+
+        // A miss on key 2 grows the target size of T1, so now T2 makes room.
+        c.insert(2, 20);
+
+        assert!(!c.contains_key(&1));
+        assert!(c.contains_key(&2));
+        assert!(c.contains_key(&3));
+    }
+
+    #[test]
+    fn test_arc_clear() {
+        let mut c = MemoCache::<i32, i32, 2, Arc>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+        c.clear();
+
+        c.insert(3, 30);
+        c.insert(4, 40);
+        c.insert(5, 50);
+
+        assert!(!c.contains_key(&3));
+        assert!(c.contains_key(&4));
+        assert!(c.contains_key(&5));
+    }
+
+    #[test]
+    fn test_random_deterministic() {
+        let mut c1 = MemoCache::<i32, i32, 8, Random>::with_policy(Random::new(7));
+        let mut c2 = MemoCache::<i32, i32, 8, Random>::with_policy(Random::new(7));
+
+        for k in 0..100 {
+            c1.insert(k, k);
+            c2.insert(k, k);
+        }
+
+        let retained = |c: &MemoCache<i32, i32, 8, Random>| {
+            (0..100).filter(|k| c.contains_key(k)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(retained(&c1).len(), 8);
+        assert_eq!(retained(&c1), retained(&c2));
+    }
+
+    #[test]
+    fn test_two_choices_deterministic() {
+        let mut c1 = MemoCache::<i32, i32, 8, TwoChoices>::with_policy(TwoChoices::new(7));
+        let mut c2 = MemoCache::<i32, i32, 8, TwoChoices>::with_policy(TwoChoices::new(7));
+
+        for k in 0..100 {
+            c1.insert(k, k);
+            c2.insert(k, k);
+
+            // Keep key 0 recently used, it is never the older of two samples.
+            assert_eq!(c1.get(&0), Some(&0));
+            assert_eq!(c2.get(&0), Some(&0));
+        }
+
+        let retained = |c: &MemoCache<i32, i32, 8, TwoChoices>| {
+            (0..100).filter(|k| c.contains_key(k)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(retained(&c1).len(), 8);
+        assert_eq!(retained(&c1), retained(&c2));
+    }
+
+    #[test]
+    fn test_slru_protects_hit_keys() {
+        let mut c = MemoCache::<i32, i32, 4, Slru<50>>::new();
+
+        c.insert(1, 10);
+        c.insert(2, 20);
+
+        assert_eq!(c.get(&1), Some(&10));
+        assert_eq!(c.get(&2), Some(&20));
+
+        // Keys 1 and 2 are protected, the scan only churns the probationary segment.
+        for k in 100..110 {
+            c.insert(k, k);
+        }
+
+        assert!(c.contains_key(&1));
+        assert!(c.contains_key(&2));
+        assert!(c.contains_key(&109));
+    }
+
+    #[test]
+    fn test_slru_demotion() {
+        let mut c = MemoCache::<i32, i32, 4, Slru<50>>::new();
+
+        for k in 1..=4 {
+            c.insert(k, k);
+        }
+
+        assert_eq!(c.get(&1), Some(&1));
+        assert_eq!(c.get(&2), Some(&2));
+
+        // The protected segment holds two keys, so promoting key 3 demotes key 1 to the probationary segment.
+        assert_eq!(c.get(&3), Some(&3));
+
+        c.insert(5, 5);
+
+        assert!(!c.contains_key(&4));
+
+        c.insert(6, 6);
+
+        assert!(!c.contains_key(&1));
+        assert!(c.contains_key(&2));
+        assert!(c.contains_key(&3));
+    }
+}
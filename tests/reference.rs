@@ -0,0 +1,60 @@
+#[macro_use]
+mod common;
+
+mod tests_external {
+    use memo_cache::{policy::Random, EvictionPolicy, MemoCacheRef, Slot};
+    use std::rc::Rc;
+
+    cache_tests! {
+        fn with_cache<K: Clone + Eq, V: Clone, P: EvictionPolicy<K, SlotState = ()>, R>(
+            f: impl FnOnce(MemoCacheRef<'_, K, V, P>) -> R,
+        ) -> R {
+            let mut slots = [const { Slot::new() }; 16];
+            f(MemoCacheRef::new(&mut slots))
+        },
+        same_as_memo_cache: Random
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_no_slots() {
+        MemoCacheRef::<u32, u32>::new(&mut []);
+    }
+
+    #[test]
+    fn test_const_slots() {
+        const SLOTS: [Slot<u32, u32>; 4] = [const { Slot::new() }; 4];
+
+        let mut slots = SLOTS;
+        let mut c = MemoCacheRef::<u32, u32>::new(&mut slots);
+
+        c.insert(1, 2);
+
+        assert_eq!(c.get(&1), Some(&2));
+    }
+
+    #[test]
+    fn test_drop_and_reuse() {
+        let value = Rc::new(());
+        let mut slots = [const { Slot::new() }; 4];
+
+        let mut c = MemoCacheRef::<u32, _>::new(&mut slots);
+
+        (0..8).for_each(|k| c.insert(k, value.clone()));
+
+        assert_eq!(Rc::strong_count(&value), 5);
+
+        drop(c);
+
+        // Used slots are dropped with the cache, after which the slots can be reused.
+        assert_eq!(Rc::strong_count(&value), 1);
+
+        let mut c = MemoCacheRef::<u32, _>::new(&mut slots);
+
+        assert_eq!(c.get(&7), None);
+
+        c.insert(7, value.clone());
+
+        assert_eq!(c.get(&7), Some(&value));
+    }
+}
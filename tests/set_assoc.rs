@@ -0,0 +1,66 @@
+#[macro_use]
+mod common;
+
+mod tests_external {
+    use memo_cache::{
+        policy::{Lru, Random},
+        EvictionPolicy, MemoCache, SetAssocMemoCache,
+    };
+    use std::hash::Hash;
+
+    // With a single set, the cache behaves exactly like the plain cache.
+    cache_tests! {
+        fn with_cache<K: Clone + Eq + Hash, V: Clone, P: EvictionPolicy<K>, R>(f: impl FnOnce(SetAssocMemoCache<K, V, 1, 16, P>) -> R) -> R {
+            f(SetAssocMemoCache::new())
+        },
+        same_as_memo_cache: Lru
+    }
+
+    #[test]
+    fn test_borrowed_key_multiple_sets() {
+        let mut c = SetAssocMemoCache::<String, i32, 4, 2>::new();
+
+        c.insert("hello".to_owned(), 42);
+
+        // Lookups by a borrowed form of the key must select the same set.
+        assert!(c.contains_key("hello"));
+        assert_eq!(c.get("hello"), Some(&42));
+        assert_eq!(c.get("world"), None);
+    }
+
+    #[test]
+    fn test_capacity_bound() {
+        let mut c = SetAssocMemoCache::<u32, u32, 8, 4>::new();
+
+        for k in 0..1_000 {
+            assert_eq!(c.get_or_insert_with(&k, |&k| k + 1), &(k + 1));
+            assert!(c.contains_key(&k));
+        }
+
+        assert!((0..1_000).filter(|k| c.contains_key(k)).count() <= c.capacity());
+    }
+
+    #[test]
+    fn test_with_policy() {
+        let mut a = SetAssocMemoCache::<u32, u32, 1, 4, Random>::with_policy(|_| Random::new(7));
+        let mut b = MemoCache::<u32, u32, 4, Random>::with_policy(Random::new(7));
+
+        // Every set uses the policy state created for it.
+        for k in 0..64 {
+            a.insert(k, k);
+            b.insert(k, k);
+        }
+
+        assert!((0..64).all(|k| a.contains_key(&k) == b.contains_key(&k)));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut c = SetAssocMemoCache::<u32, u32, 4, 2>::new();
+
+        (0..16).for_each(|k| c.insert(k, k));
+        c.clear();
+
+        assert!((0..16).all(|k| !c.contains_key(&k)));
+    }
+}
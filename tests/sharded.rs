@@ -0,0 +1,146 @@
+mod tests_external {
+    use memo_cache::{
+        policy::{Lru, Random},
+        CacheStats, MemoCache, ShardedMemoCache,
+    };
+    use std::thread;
+
+    #[test]
+    fn test_empty() {
+        let c = ShardedMemoCache::<bool, bool, 2, 2>::new();
+
+        assert_eq!(c.capacity(), 4);
+        assert_eq!(c.get(&true), None);
+        assert_eq!(c.get(&false), None);
+    }
+
+    #[test]
+    fn test_borrowed_key() {
+        let c = ShardedMemoCache::<String, i32, 4, 3>::new();
+
+        c.insert("hello".to_owned(), 42);
+
+        assert!(c.contains_key("hello"));
+        assert_eq!(c.get("hello"), Some(42));
+        assert_eq!(c.get("world"), None);
+    }
+
+    #[test]
+    fn test_single_shard() {
+        // With a single shard, the cache behaves like a plain cache.
+        let c = ShardedMemoCache::<u32, u32, 1, 4, Lru>::new();
+        let mut m = MemoCache::<u32, u32, 4, Lru>::new();
+
+        for k in [1, 2, 3, 1, 4, 5, 2, 6, 1, 7, 3, 3, 8] {
+            assert_eq!(
+                c.get_or_insert_with(&k, |&k| k * 2),
+                *m.get_or_insert_with(&k, |&k| k * 2)
+            );
+        }
+
+        for k in 0..10 {
+            assert_eq!(c.get(&k), m.get(&k).copied());
+        }
+    }
+
+    #[test]
+    fn test_with_policy() {
+        let c = ShardedMemoCache::<u32, u32, 1, 4, Random>::with_policy(|_| Random::new(7));
+        let mut m = MemoCache::<u32, u32, 4, Random>::with_policy(Random::new(7));
+
+        // Every shard uses the policy state created for it.
+        for k in 0..64 {
+            c.insert(k, k);
+            m.insert(k, k);
+        }
+
+        assert!((0..64).all(|k| c.contains_key(&k) == m.contains_key(&k)));
+    }
+
+    #[test]
+    fn test_capacity_bound() {
+        let c = ShardedMemoCache::<u32, u32, 4, 2>::new();
+
+        (0..100).for_each(|k| c.insert(k, k));
+
+        assert!(c.stats().len <= c.capacity());
+        assert!((0..100).filter(|k| c.contains_key(k)).count() <= c.capacity());
+    }
+
+    #[test]
+    fn test_get_or_try_insert_with() {
+        let c = ShardedMemoCache::<u32, u32, 4, 2>::new();
+
+        assert_eq!(
+            c.get_or_try_insert_with(&1, |_| Err::<u32, _>("fail")),
+            Err("fail")
+        );
+        assert_eq!(c.get_or_try_insert_with(&1, |_| Ok::<_, &str>(10)), Ok(10));
+        assert_eq!(c.get_or_try_insert_with(&1, |_| Err("fail")), Ok(10));
+    }
+
+    #[test]
+    fn test_stats() {
+        let c = ShardedMemoCache::<u32, u32, 4, 8>::new();
+
+        (0..10).for_each(|k| c.insert(k, k));
+
+        // Direct inserts and key checks are not counted as lookups.
+        assert!(c.contains_key(&3));
+        assert_eq!(
+            c.stats(),
+            CacheStats {
+                hits: 0,
+                misses: 0,
+                len: 10
+            }
+        );
+
+        (0..20).for_each(|k| {
+            c.get(&k);
+        });
+
+        assert_eq!(
+            c.stats(),
+            CacheStats {
+                hits: 10,
+                misses: 10,
+                len: 10
+            }
+        );
+
+        c.clear();
+
+        assert_eq!(
+            c.stats(),
+            CacheStats {
+                hits: 10,
+                misses: 10,
+                len: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_threads() {
+        static CACHE: ShardedMemoCache<u32, u64, 4, 8> = ShardedMemoCache::new();
+
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for k in 0..100 {
+                        assert_eq!(
+                            CACHE.get_or_insert_with(&k, |&k| u64::from(k) * 3),
+                            u64::from(k) * 3
+                        );
+                    }
+                });
+            }
+        });
+
+        let stats = CACHE.stats();
+
+        assert_eq!(stats.hits + stats.misses, 4 * 100);
+        assert!(stats.len <= CACHE.capacity());
+    }
+}
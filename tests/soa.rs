@@ -0,0 +1,13 @@
+#[macro_use]
+mod common;
+
+mod tests_external {
+    use memo_cache::{policy::Lru, EvictionPolicy, SoaMemoCache};
+
+    cache_tests! {
+        fn with_cache<K: Clone + Eq, V: Clone, P: EvictionPolicy<K>, R>(f: impl FnOnce(SoaMemoCache<K, V, 16, P>) -> R) -> R {
+            f(SoaMemoCache::new())
+        },
+        same_as_memo_cache: Lru
+    }
+}
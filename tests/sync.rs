@@ -0,0 +1,413 @@
+mod tests_external {
+    use futures::{channel::oneshot, executor::block_on, future, join, poll};
+    use memo_cache::{policy::Lru, MemoCache, SyncMemoCache};
+    use std::{
+        panic,
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            mpsc, Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    /// Result of a fallible calculation.
+    type Calculated = Result<u32, String>;
+
+    /// Run `calculation` for key 1 in one thread, and have `WAITERS` other threads miss on the same key while it runs.
+    /// Returns the results of the calculating thread and the waiting threads.
+    fn run_concurrent_misses<const WAITERS: usize>(
+        c: &SyncMemoCache<u32, u32, 4>,
+        calculations: &AtomicUsize,
+        calculation: impl FnOnce() -> Calculated + Send,
+    ) -> (thread::Result<Calculated>, [Calculated; WAITERS]) {
+        let (started_tx, started_rx) = mpsc::channel();
+
+        thread::scope(|s| {
+            let first = s.spawn(|| {
+                c.get_or_try_insert_with(&1, |_| {
+                    calculations.fetch_add(1, Ordering::SeqCst);
+                    started_tx.send(()).unwrap();
+
+                    // Give the other threads time to miss on the key.
+                    thread::sleep(Duration::from_millis(100));
+                    calculation()
+                })
+            });
+
+            started_rx.recv().unwrap();
+
+            let waiters = [(); WAITERS].map(|_| {
+                s.spawn(|| {
+                    c.get_or_try_insert_with(&1, |_| {
+                        calculations.fetch_add(1, Ordering::SeqCst);
+                        Ok(2)
+                    })
+                })
+            });
+
+            (first.join(), waiters.map(|w| w.join().unwrap()))
+        })
+    }
+
+    #[test]
+    fn test_empty() {
+        let c = SyncMemoCache::<bool, bool, 2>::new();
+
+        assert_eq!(c.capacity(), 2);
+        assert_eq!(c.get(&true), None);
+        assert_eq!(c.get(&false), None);
+    }
+
+    #[test]
+    fn test_borrowed_key() {
+        let c = SyncMemoCache::<String, i32, 3>::new();
+
+        c.insert("hello".to_owned(), 42);
+
+        assert!(c.contains_key("hello"));
+        assert_eq!(c.get("hello"), Some(42));
+        assert_eq!(c.get("world"), None);
+    }
+
+    #[test]
+    fn test_get_or_try_insert_with() {
+        let c = SyncMemoCache::<u32, u32, 2>::new();
+
+        assert_eq!(
+            c.get_or_try_insert_with(&1, |_| Err::<u32, _>("fail")),
+            Err("fail")
+        );
+        assert_eq!(c.get_or_try_insert_with(&1, |_| Ok::<_, &str>(10)), Ok(10));
+        assert_eq!(c.get_or_try_insert_with(&1, |_| Err("fail")), Ok(10));
+    }
+
+    #[test]
+    fn test_lock_released_during_calculation() {
+        let c = SyncMemoCache::<u32, u32, 4>::new();
+
+        // Accessing the cache from within the calculation would deadlock if the lock were held.
+        let v = c.get_or_insert_with(&1, |&k| c.get_or_insert_with(&(k + 1), |&k| k * 10) + 1);
+
+        assert_eq!(v, 21);
+        assert_eq!(c.get(&1), Some(21));
+        assert_eq!(c.get(&2), Some(20));
+    }
+
+    #[test]
+    fn test_first_insert_kept() {
+        let c = SyncMemoCache::<u32, u32, 4>::new();
+
+        // A value inserted while calculating takes precedence over the calculated value.
+        let v = c.get_or_insert_with(&1, |_| {
+            c.insert(1, 10);
+            20
+        });
+
+        assert_eq!(v, 10);
+        assert_eq!(c.get(&1), Some(10));
+    }
+
+    #[test]
+    fn test_single_flight() {
+        let c = SyncMemoCache::<u32, u32, 4>::new();
+        let calculations = AtomicUsize::new(0);
+
+        let (first, waiters) = run_concurrent_misses::<4>(&c, &calculations, || Ok(1));
+
+        assert_eq!(first.unwrap(), Ok(1));
+        assert_eq!(waiters, [Ok(1), Ok(1), Ok(1), Ok(1)]);
+        assert_eq!(calculations.load(Ordering::SeqCst), 1);
+        assert_eq!(c.get(&1), Some(1));
+    }
+
+    #[test]
+    fn test_single_flight_error() {
+        let c = SyncMemoCache::<u32, u32, 4>::new();
+        let calculations = AtomicUsize::new(0);
+
+        let (first, waiters) =
+            run_concurrent_misses::<4>(&c, &calculations, || Err("fail".to_owned()));
+
+        // The error is shared with all waiting threads, but not cached.
+        assert_eq!(first.unwrap(), Err("fail".to_owned()));
+        assert!(waiters.iter().all(|w| w == &Err("fail".to_owned())));
+        assert_eq!(calculations.load(Ordering::SeqCst), 1);
+        assert_eq!(c.get(&1), None);
+    }
+
+    #[test]
+    fn test_single_flight_panic() {
+        let c = SyncMemoCache::<u32, u32, 4>::new();
+        let calculations = AtomicUsize::new(0);
+
+        let (first, waiters) =
+            run_concurrent_misses::<4>(&c, &calculations, || panic!("calculation failed"));
+
+        // One of the waiting threads takes over the calculation.
+        assert!(first.is_err());
+        assert_eq!(waiters, [Ok(2), Ok(2), Ok(2), Ok(2)]);
+        assert_eq!(calculations.load(Ordering::SeqCst), 2);
+        assert_eq!(c.get(&1), Some(2));
+    }
+
+    #[test]
+    fn test_single_flight_panicking_clone() {
+        static ARMED: AtomicBool = AtomicBool::new(false);
+
+        /// Value of which the next clone panics once armed.
+        #[derive(Debug, PartialEq)]
+        struct Value(u32);
+
+        impl Clone for Value {
+            fn clone(&self) -> Self {
+                assert!(!ARMED.swap(false, Ordering::SeqCst), "clone failed");
+                Self(self.0)
+            }
+        }
+
+        let c = SyncMemoCache::<u32, Value, 4>::new();
+        let (started_tx, started_rx) = mpsc::channel();
+
+        thread::scope(|s| {
+            let first = s.spawn(|| {
+                c.get_or_insert_with(&1, |_| {
+                    started_tx.send(()).unwrap();
+
+                    // Wait for the other threads to miss on the key, then fail cloning the inserted value.
+                    while c.stats().misses < 5 {
+                        thread::yield_now();
+                    }
+
+                    ARMED.store(true, Ordering::SeqCst);
+                    Value(1)
+                })
+            });
+
+            started_rx.recv().unwrap();
+
+            let waiters = [(); 4].map(|_| s.spawn(|| c.get_or_insert_with(&1, |_| Value(2))));
+
+            // The flight is abandoned, and the waiting threads find the inserted value.
+            assert!(first.join().is_err());
+            assert!(waiters.into_iter().all(|w| w.join().unwrap() == Value(1)));
+        });
+    }
+
+    #[test]
+    fn test_panicking_calculation() {
+        let c = SyncMemoCache::<u32, u32, 4>::new();
+
+        c.insert(1, 10);
+
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            c.get_or_insert_with(&2, |_| panic!("calculation failed"))
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(c.get(&1), Some(10));
+        assert_eq!(c.get(&2), None);
+    }
+
+    #[test]
+    fn test_panicking_key() {
+        use memo_cache::policy::S3Fifo;
+        use std::hash::{Hash, Hasher};
+
+        /// Key of which the hash panics if so requested.
+        #[derive(Clone, PartialEq, Eq)]
+        struct Key(u32, bool);
+
+        impl Hash for Key {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                assert!(!self.1, "hash failed");
+                self.0.hash(state);
+            }
+        }
+
+        let c = SyncMemoCache::<Key, u32, 2, S3Fifo>::new();
+
+        c.insert(Key(1, false), 10);
+        c.insert(Key(2, false), 20);
+
+        // The key is hashed while the lock is held.
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| c.insert(Key(3, true), 30)));
+
+        assert!(result.is_err());
+
+        // The lock is not poisoned, and the cache was emptied.
+        assert_eq!(c.get(&Key(1, false)), None);
+        assert_eq!(c.get(&Key(2, false)), None);
+
+        for k in 0..10 {
+            c.insert(Key(k, false), k);
+            assert_eq!(c.get(&Key(k, false)), Some(k));
+        }
+    }
+
+    #[test]
+    fn test_same_as_memo_cache() {
+        let c = SyncMemoCache::<u32, u32, 4, Lru>::new();
+        let mut m = MemoCache::<u32, u32, 4, Lru>::new();
+
+        for k in [1, 2, 3, 1, 4, 5, 2, 6, 1, 7, 3, 3, 8] {
+            assert_eq!(
+                c.get_or_insert_with(&k, |&k| k * 2),
+                *m.get_or_insert_with(&k, |&k| k * 2)
+            );
+        }
+
+        for k in 0..10 {
+            assert_eq!(c.get(&k), m.get(&k).copied());
+        }
+    }
+
+    #[test]
+    fn test_threads() {
+        static CACHE: SyncMemoCache<u32, u64, 8> = SyncMemoCache::new();
+
+        let calculations = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let calculations = Arc::clone(&calculations);
+                thread::spawn(move || {
+                    for k in 0..100 {
+                        let v = CACHE.get_or_insert_with(&(k % 8), |&k| {
+                            calculations.fetch_add(1, Ordering::Relaxed);
+                            u64::from(k) * 3
+                        });
+
+                        assert_eq!(v, u64::from(k % 8) * 3);
+                    }
+                })
+            })
+            .collect();
+
+        handles.into_iter().for_each(|h| h.join().unwrap());
+
+        // All keys fit, and concurrent misses are deduplicated, so every key is calculated exactly once.
+        assert_eq!(calculations.load(Ordering::Relaxed), 8);
+    }
+
+    #[test]
+    fn test_async() {
+        let c = SyncMemoCache::<u32, u32, 2>::new();
+
+        assert_eq!(
+            block_on(c.get_or_insert_with_async(&1, |&k| async move { k * 10 })),
+            10
+        );
+        assert_eq!(
+            block_on(c.get_or_insert_with_async(&1, |_| async { 20 })),
+            10
+        );
+
+        let fail = |_: &u32| async { Err::<u32, _>("fail") };
+
+        assert_eq!(
+            block_on(c.get_or_try_insert_with_async(&2, fail)),
+            Err("fail")
+        );
+        assert_eq!(c.get(&2), None);
+        assert_eq!(block_on(c.get_or_try_insert_with_async(&1, fail)), Ok(10));
+    }
+
+    #[test]
+    fn test_async_send() {
+        fn assert_send<T: Send>(_: T) {}
+
+        let c = SyncMemoCache::<u32, String, 2>::new();
+
+        assert_send(c.get_or_insert_with_async(&1, |_| async { String::new() }));
+        assert_send(
+            c.get_or_try_insert_with_async(&1, |_| async { Ok::<_, String>(String::new()) }),
+        );
+    }
+
+    #[test]
+    fn test_async_single_flight() {
+        let c = SyncMemoCache::<u32, u32, 4>::new();
+        let calculations = AtomicUsize::new(0);
+        let (tx, rx) = oneshot::channel();
+
+        let calculate = |_: &u32| {
+            calculations.fetch_add(1, Ordering::SeqCst);
+            async { 2 }
+        };
+
+        // The first future starts the calculation, and the others await it, until the value is sent.
+        let results = block_on(async {
+            join!(
+                c.get_or_insert_with_async(&1, |_| {
+                    calculations.fetch_add(1, Ordering::SeqCst);
+                    async { rx.await.unwrap() }
+                }),
+                c.get_or_insert_with_async(&1, calculate),
+                c.get_or_insert_with_async(&1, calculate),
+                async { tx.send(1).unwrap() },
+            )
+        });
+
+        assert_eq!(results, (1, 1, 1, ()));
+        assert_eq!(calculations.load(Ordering::SeqCst), 1);
+        assert_eq!(c.get(&1), Some(1));
+    }
+
+    #[test]
+    fn test_async_single_flight_error() {
+        let c = SyncMemoCache::<u32, u32, 4>::new();
+        let (tx, rx) = oneshot::channel();
+
+        let results = block_on(async {
+            join!(
+                c.get_or_try_insert_with_async(&1, |_| async { rx.await.unwrap() }),
+                c.get_or_try_insert_with_async(&1, |_| async { Ok(2) }),
+                async { tx.send(Err("fail")).unwrap() },
+            )
+        });
+
+        // The error is shared with the waiting task, but not cached.
+        assert_eq!(results, (Err("fail"), Err("fail"), ()));
+        assert_eq!(c.get(&1), None);
+    }
+
+    #[test]
+    fn test_async_cancelled() {
+        let c = SyncMemoCache::<u32, u32, 4>::new();
+
+        block_on(async {
+            let mut first = Box::pin(c.get_or_insert_with_async(&1, |_| future::pending()));
+            assert!(poll!(first.as_mut()).is_pending());
+
+            let mut waiter = Box::pin(c.get_or_insert_with_async(&1, |_| async { 2 }));
+            assert!(poll!(waiter.as_mut()).is_pending());
+
+            // Dropping the calculating future makes the waiting task take over.
+            drop(first);
+
+            assert_eq!(waiter.await, 2);
+        });
+
+        assert_eq!(c.get(&1), Some(2));
+    }
+
+    #[test]
+    fn test_async_blocking_waiter() {
+        let c = SyncMemoCache::<u32, u32, 4>::new();
+        let (tx, rx) = oneshot::channel();
+
+        let mut first = Box::pin(c.get_or_insert_with_async(&1, |_| async { rx.await.unwrap() }));
+        assert!(block_on(async { poll!(first.as_mut()) }).is_pending());
+
+        // A blocking call on another thread waits for the value calculated by the task.
+        thread::scope(|s| {
+            let waiter = s.spawn(|| c.get_or_insert_with(&1, |_| 2));
+
+            tx.send(1).unwrap();
+
+            assert_eq!(block_on(first), 1);
+            assert_eq!(waiter.join().unwrap(), 1);
+        });
+    }
+}
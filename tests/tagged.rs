@@ -0,0 +1,41 @@
+#[macro_use]
+mod common;
+
+mod tests_external {
+    use memo_cache::{policy::Lru, EvictionPolicy, TaggedMemoCache};
+    use std::hash::Hash;
+
+    cache_tests! {
+        fn with_cache<K: Clone + Eq + Hash, V: Clone, P: EvictionPolicy<K>, R>(f: impl FnOnce(TaggedMemoCache<K, V, 16, P>) -> R) -> R {
+            f(TaggedMemoCache::new())
+        },
+        same_as_memo_cache: Lru
+    }
+
+    #[test]
+    fn test_insert_existing() {
+        let mut c = TaggedMemoCache::<u32, &str, 2>::new();
+
+        c.insert(1, "one");
+        c.insert(1, "uno");
+        c.insert(2, "two");
+
+        // Updating a key in place must not take a slot.
+        assert_eq!(c.get(&1), Some(&"uno"));
+        assert_eq!(c.get(&2), Some(&"two"));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut c = TaggedMemoCache::<u32, u32, 4>::new();
+
+        (0..8).for_each(|k| c.insert(k, k));
+        c.clear();
+
+        assert!((0..8).all(|k| !c.contains_key(&k)));
+
+        c.insert(42, 42);
+
+        assert_eq!(c.get(&42), Some(&42));
+    }
+}